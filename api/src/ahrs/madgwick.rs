@@ -0,0 +1,242 @@
+//! Madgwick gradient-descent attitude filter.
+
+use math::{
+    inv_sqrt,
+    sqrt,
+    Quaternion,
+};
+
+use crate::imu::{
+    Accel,
+    Gyro,
+    Mag,
+    Angles,
+};
+use super::{
+    to_angles,
+    TO_RADIANS,
+};
+
+
+/// Default gradient-descent gain of the Madgwick filter.
+const DEFAULT_MADGWICK_BETA: f32 = 0.1;
+
+
+/// Defines a Madgwick attitude filter.
+///
+/// The gyroscope rates are integrated every timestep and corrected by one gradient-descent step
+/// towards the orientation that best explains the measured gravity (and, if given, magnetic field) directions.
+/// The gain `beta` sets the size of that step: larger values converge faster but pass through more accelerometer noise.
+///
+/// Without a magnetometer, yaw drifts with the (calibrated) gyroscope.
+/// With a magnetometer (see `Madgwick::update_with_mag`), heading is referenced to magnetic north.
+///
+/// # Examples
+/// ```
+/// use api::ahrs::Madgwick;
+/// use api::imu::{Accel, Gyro, Mag};
+///
+/// // Sit level and still, yawed 30 degrees away from magnetic north
+/// let mut ahrs = Madgwick::new(1000);
+/// ahrs.set_beta(0.5);
+/// let accel = Accel { x: 0.0, y: 0.0, z: 1.0 };
+/// let gyro = Gyro { x: 0.0, y: 0.0, z: 0.0 };
+/// let mag = Mag { x: 0.866, y: -0.5, z: 0.5 };
+/// for _ in 0..5000 {
+///     ahrs.update_with_mag(&accel, &gyro, &mag);
+/// }
+///
+/// assert!((ahrs.heading() - 30.0*60.0).abs() < 30.0);
+/// ```
+pub struct Madgwick {
+    quaternion: Quaternion<f32>,
+    timestep: u16,
+    beta: f32,
+}
+
+impl Madgwick {
+    /// Constructs a new Madgwick filter that is updated every `timestep` microseconds.
+    pub fn new(timestep: u16) -> Self {
+        Madgwick {
+            quaternion: Quaternion::identity(),
+            timestep: timestep,
+            beta: DEFAULT_MADGWICK_BETA,
+        }
+    }
+
+    /// Sets the gradient-descent gain.
+    pub fn set_beta(&mut self, beta: f32) {
+        self.beta = beta;
+    }
+
+    /// Resets the estimate to level with zero heading.
+    pub fn reset(&mut self) {
+        self.quaternion = Quaternion::identity();
+    }
+
+    /// Updates the attitude estimate over one nominal timestep with one accelerometer sample and one gyroscope sample.
+    pub fn update(&mut self, accel: &Accel, gyro: &Gyro) {
+        self.update_dt(accel, gyro, self.timestep as u32);
+    }
+
+    /// Updates the attitude estimate with one accelerometer sample and one gyroscope sample,
+    /// integrating over the measured interval `dt` (in microseconds) since the previous update.
+    ///
+    /// # Examples
+    /// ```
+    /// use api::ahrs::Madgwick;
+    /// use api::imu::{Accel, Gyro};
+    ///
+    /// // A loop that runs every 2.5 ms instead of the nominal 1 ms still tracks a 90 degree turn in one second
+    /// let mut ahrs = Madgwick::new(1000);
+    /// let accel = Accel { x: 0.0, y: 0.0, z: 1.0 };
+    /// let gyro = Gyro { x: 0.0, y: 0.0, z: 90.0 };
+    /// for _ in 0..400 {
+    ///     ahrs.update_dt(&accel, &gyro, 2500);
+    /// }
+    ///
+    /// assert!((ahrs.angles().yaw - 90.0*60.0).abs() < 30.0);
+    /// ```
+    pub fn update_dt(&mut self, accel: &Accel, gyro: &Gyro, dt: u32) {
+        let q = self.quaternion;
+        let (q0, q1, q2, q3) = (q.w, q.x, q.y, q.z);
+        let (gx, gy, gz) = (gyro.x*TO_RADIANS, gyro.y*TO_RADIANS, gyro.z*TO_RADIANS);
+
+        // Rate of change of the quaternion from the gyroscope
+        let mut rate: [f32; 4] = [
+            0.5*(-q1*gx - q2*gy - q3*gz),
+            0.5*(q0*gx + q2*gz - q3*gy),
+            0.5*(q0*gy - q1*gz + q3*gx),
+            0.5*(q0*gz + q1*gy - q2*gx),
+        ];
+
+        // Only use the accelerometer if it measured something (it reads zero in free fall)
+        let norm_squared: f32 = accel.x*accel.x + accel.y*accel.y + accel.z*accel.z;
+        if norm_squared > 0.0 {
+            let norm = inv_sqrt(norm_squared);
+            let (ax, ay, az) = (accel.x*norm, accel.y*norm, accel.z*norm);
+
+            let (q0q0, q1q1, q2q2, q3q3) = (q0*q0, q1*q1, q2*q2, q3*q3);
+
+            // Gradient of the gravity objective function
+            let s0: f32 = 4.0*q0*q2q2 + 2.0*q2*ax + 4.0*q0*q1q1 - 2.0*q1*ay;
+            let s1: f32 = 4.0*q1*q3q3 - 2.0*q3*ax + 4.0*q0q0*q1 - 2.0*q0*ay - 4.0*q1
+                + 8.0*q1*q1q1 + 8.0*q1*q2q2 + 4.0*q1*az;
+            let s2: f32 = 4.0*q0q0*q2 + 2.0*q0*ax + 4.0*q2*q3q3 - 2.0*q3*ay - 4.0*q2
+                + 8.0*q2*q1q1 + 8.0*q2*q2q2 + 4.0*q2*az;
+            let s3: f32 = 4.0*q1q1*q3 - 2.0*q1*ax + 4.0*q2q2*q3 - 2.0*q2*ay;
+
+            self.descend(&mut rate, [s0, s1, s2, s3]);
+        }
+
+        self.integrate(rate, dt);
+    }
+
+    /// Updates the attitude estimate over one nominal timestep with one accelerometer sample, one gyroscope sample, and one magnetometer sample.
+    /// If the magnetometer reads zero, this falls back to `Madgwick::update`.
+    pub fn update_with_mag(&mut self, accel: &Accel, gyro: &Gyro, mag: &Mag) {
+        self.update_with_mag_dt(accel, gyro, mag, self.timestep as u32);
+    }
+
+    /// Updates the attitude estimate with one accelerometer sample, one gyroscope sample, and one magnetometer sample,
+    /// integrating over the measured interval `dt` (in microseconds) since the previous update.
+    /// If the magnetometer reads zero, this falls back to `Madgwick::update_dt`.
+    pub fn update_with_mag_dt(&mut self, accel: &Accel, gyro: &Gyro, mag: &Mag, dt: u32) {
+        let accel_norm_squared: f32 = accel.x*accel.x + accel.y*accel.y + accel.z*accel.z;
+        let mag_norm_squared: f32 = mag.x*mag.x + mag.y*mag.y + mag.z*mag.z;
+        if accel_norm_squared == 0.0 || mag_norm_squared == 0.0 {
+            self.update_dt(accel, gyro, dt);
+            return;
+        }
+
+        let q = self.quaternion;
+        let (q0, q1, q2, q3) = (q.w, q.x, q.y, q.z);
+        let (gx, gy, gz) = (gyro.x*TO_RADIANS, gyro.y*TO_RADIANS, gyro.z*TO_RADIANS);
+
+        // Rate of change of the quaternion from the gyroscope
+        let mut rate: [f32; 4] = [
+            0.5*(-q1*gx - q2*gy - q3*gz),
+            0.5*(q0*gx + q2*gz - q3*gy),
+            0.5*(q0*gy - q1*gz + q3*gx),
+            0.5*(q0*gz + q1*gy - q2*gx),
+        ];
+
+        let norm = inv_sqrt(accel_norm_squared);
+        let (ax, ay, az) = (accel.x*norm, accel.y*norm, accel.z*norm);
+        let norm = inv_sqrt(mag_norm_squared);
+        let (mx, my, mz) = (mag.x*norm, mag.y*norm, mag.z*norm);
+
+        let (q0q0, q0q1, q0q2, q0q3) = (q0*q0, q0*q1, q0*q2, q0*q3);
+        let (q1q1, q1q2, q1q3) = (q1*q1, q1*q2, q1*q3);
+        let (q2q2, q2q3) = (q2*q2, q2*q3);
+        let q3q3: f32 = q3*q3;
+
+        // Direction of the Earth's magnetic field in the Earth frame
+        let hx: f32 = mx*q0q0 - 2.0*q0*my*q3 + 2.0*q0*mz*q2 + mx*q1q1 + 2.0*q1*my*q2 + 2.0*q1*mz*q3
+            - mx*q2q2 - mx*q3q3;
+        let hy: f32 = 2.0*q0*mx*q3 + my*q0q0 - 2.0*q0*mz*q1 + 2.0*q1*mx*q2 - my*q1q1 + my*q2q2
+            + 2.0*q2*mz*q3 - my*q3q3;
+        let bx: f32 = sqrt(hx*hx + hy*hy);
+        let bz: f32 = -2.0*q0*mx*q2 + 2.0*q0*my*q1 + mz*q0q0 + 2.0*q1*mx*q3 - mz*q1q1
+            + 2.0*q2*my*q3 - mz*q2q2 + mz*q3q3;
+
+        // Residuals of the gravity and magnetic field objective functions
+        let fax: f32 = 2.0*(q1q3 - q0q2) - ax;
+        let fay: f32 = 2.0*(q0q1 + q2q3) - ay;
+        let faz: f32 = 1.0 - 2.0*(q1q1 + q2q2) - az;
+        let fmx: f32 = bx*(1.0 - 2.0*(q2q2 + q3q3)) + 2.0*bz*(q1q3 - q0q2) - mx;
+        let fmy: f32 = 2.0*bx*(q1q2 - q0q3) + 2.0*bz*(q0q1 + q2q3) - my;
+        let fmz: f32 = 2.0*bx*(q0q2 + q1q3) + bz*(1.0 - 2.0*(q1q1 + q2q2)) - mz;
+
+        // Gradient of the objective functions (the transposed Jacobian times the residuals)
+        let s0: f32 = -2.0*q2*fax + 2.0*q1*fay
+            - 2.0*bz*q2*fmx + 2.0*(-bx*q3 + bz*q1)*fmy + 2.0*bx*q2*fmz;
+        let s1: f32 = 2.0*q3*fax + 2.0*q0*fay - 4.0*q1*faz
+            + 2.0*bz*q3*fmx + 2.0*(bx*q2 + bz*q0)*fmy + 2.0*(bx*q3 - 2.0*bz*q1)*fmz;
+        let s2: f32 = -2.0*q0*fax + 2.0*q3*fay - 4.0*q2*faz
+            + 2.0*(-2.0*bx*q2 - bz*q0)*fmx + 2.0*(bx*q1 + bz*q3)*fmy + 2.0*(bx*q0 - 2.0*bz*q2)*fmz;
+        let s3: f32 = 2.0*q1*fax + 2.0*q2*fay
+            + 2.0*(-2.0*bx*q3 + bz*q1)*fmx + 2.0*(-bx*q0 + bz*q2)*fmy + 2.0*bx*q1*fmz;
+
+        self.descend(&mut rate, [s0, s1, s2, s3]);
+        self.integrate(rate, dt);
+    }
+
+    /// Returns the current attitude estimate as a quaternion.
+    pub fn quaternion(&self) -> Quaternion<f32> {
+        self.quaternion
+    }
+
+    /// Returns the current attitude estimate as roll, pitch, and yaw angles (in minutes of arc).
+    pub fn angles(&self) -> Angles {
+        to_angles(&self.quaternion)
+    }
+
+    /// Returns the current heading (in minutes of arc), using the same convention as the `yaw` field of `Angles`.
+    pub fn heading(&self) -> f32 {
+        self.angles().yaw
+    }
+
+    /// Subtracts the normalized gradient, scaled by `beta`, from the rate of change of the quaternion.
+    fn descend(&self, rate: &mut [f32; 4], gradient: [f32; 4]) {
+        let norm_squared: f32 = gradient.iter().map(|s| s*s).sum();
+        if norm_squared > 0.0 {
+            let norm = inv_sqrt(norm_squared);
+            for (r, s) in rate.iter_mut().zip(gradient.iter()) {
+                *r -= self.beta*s*norm;
+            }
+        }
+    }
+
+    /// Integrates the rate of change of the quaternion over `dt` microseconds.
+    fn integrate(&mut self, rate: [f32; 4], dt: u32) {
+        let dt: f32 = (dt as f32)*1e-6;
+        let q = self.quaternion;
+        self.quaternion = Quaternion {
+            w: q.w + rate[0]*dt,
+            x: q.x + rate[1]*dt,
+            y: q.y + rate[2]*dt,
+            z: q.z + rate[3]*dt,
+        }.normalize();
+    }
+}
@@ -0,0 +1,161 @@
+//! Mahony complementary attitude filter.
+
+use math::{
+    inv_sqrt,
+    Quaternion,
+};
+
+use crate::imu::{
+    Accel,
+    Gyro,
+    Angles,
+};
+use super::{
+    to_angles,
+    TO_RADIANS,
+};
+
+
+/// Default proportional gain of the Mahony filter.
+/// Larger values trust the accelerometer more and the gyroscope less.
+const DEFAULT_MAHONY_KP: f32 = 1.0;
+
+/// Default integral gain of the Mahony filter.
+/// Setting this to zero turns the Mahony filter into a plain complementary filter.
+const DEFAULT_MAHONY_KI: f32 = 0.01;
+
+
+/// Defines a Mahony attitude filter.
+///
+/// The gyroscope rates are integrated every timestep and the drift in roll and pitch is corrected
+/// by proportional-integral feedback towards the gravity vector measured by the accelerometer.
+/// With an integral gain of zero, this is a complementary filter.
+/// Yaw is not observable by the accelerometer, so yaw drifts with the (calibrated) gyroscope.
+///
+/// # Examples
+/// ```
+/// use api::ahrs::Mahony;
+/// use api::imu::{Accel, Gyro};
+///
+/// // Sit level while yawing at 90 degrees per second for one second
+/// let mut ahrs = Mahony::new(1000);
+/// let accel = Accel { x: 0.0, y: 0.0, z: 1.0 };
+/// let gyro = Gyro { x: 0.0, y: 0.0, z: 90.0 };
+/// for _ in 0..1000 {
+///     ahrs.update(&accel, &gyro);
+/// }
+///
+/// let angles = ahrs.angles();
+/// assert!((angles.yaw - 90.0*60.0).abs() < 30.0);
+/// assert!(angles.roll.abs() < 1.0 && angles.pitch.abs() < 1.0);
+/// ```
+pub struct Mahony {
+    quaternion: Quaternion<f32>,
+    timestep: u16,
+    kp: f32,
+    ki: f32,
+    integral: [f32; 3],
+}
+
+impl Mahony {
+    /// Constructs a new Mahony filter that is updated every `timestep` microseconds.
+    pub fn new(timestep: u16) -> Self {
+        Mahony {
+            quaternion: Quaternion::identity(),
+            timestep: timestep,
+            kp: DEFAULT_MAHONY_KP,
+            ki: DEFAULT_MAHONY_KI,
+            integral: [0.0; 3],
+        }
+    }
+
+    /// Sets the proportional and integral feedback gains.
+    pub fn set_gains(&mut self, kp: f32, ki: f32) {
+        self.kp = kp;
+        self.ki = ki;
+    }
+
+    /// Resets the estimate to level with zero heading and clears the integral feedback.
+    pub fn reset(&mut self) {
+        self.quaternion = Quaternion::identity();
+        self.integral = [0.0; 3];
+    }
+
+    /// Updates the attitude estimate over one nominal timestep with one accelerometer sample and one gyroscope sample.
+    pub fn update(&mut self, accel: &Accel, gyro: &Gyro) {
+        self.update_dt(accel, gyro, self.timestep as u32);
+    }
+
+    /// Updates the attitude estimate with one accelerometer sample and one gyroscope sample,
+    /// integrating the gyroscope over the measured interval `dt` (in microseconds) since the previous update.
+    ///
+    /// # Examples
+    /// ```
+    /// use api::ahrs::Mahony;
+    /// use api::imu::{Accel, Gyro};
+    ///
+    /// // A loop that runs every 2.5 ms instead of the nominal 1 ms still tracks a 90 degree turn in one second
+    /// let mut ahrs = Mahony::new(1000);
+    /// let accel = Accel { x: 0.0, y: 0.0, z: 1.0 };
+    /// let gyro = Gyro { x: 0.0, y: 0.0, z: 90.0 };
+    /// for _ in 0..400 {
+    ///     ahrs.update_dt(&accel, &gyro, 2500);
+    /// }
+    ///
+    /// assert!((ahrs.angles().yaw - 90.0*60.0).abs() < 30.0);
+    /// ```
+    pub fn update_dt(&mut self, accel: &Accel, gyro: &Gyro, dt: u32) {
+        let dt: f32 = (dt as f32)*1e-6;
+        let q = self.quaternion;
+
+        let mut gx: f32 = gyro.x*TO_RADIANS;
+        let mut gy: f32 = gyro.y*TO_RADIANS;
+        let mut gz: f32 = gyro.z*TO_RADIANS;
+
+        // Only use the accelerometer if it measured something (it reads zero in free fall)
+        let norm_squared: f32 = accel.x*accel.x + accel.y*accel.y + accel.z*accel.z;
+        if norm_squared > 0.0 {
+            let norm = inv_sqrt(norm_squared);
+            let (ax, ay, az) = (accel.x*norm, accel.y*norm, accel.z*norm);
+
+            // Direction of gravity as predicted by the current estimate
+            let vx: f32 = 2.0*(q.x*q.z - q.w*q.y);
+            let vy: f32 = 2.0*(q.w*q.x + q.y*q.z);
+            let vz: f32 = q.w*q.w - q.x*q.x - q.y*q.y + q.z*q.z;
+
+            // Error is the cross product between the measured and predicted directions
+            let ex: f32 = ay*vz - az*vy;
+            let ey: f32 = az*vx - ax*vz;
+            let ez: f32 = ax*vy - ay*vx;
+
+            if self.ki > 0.0 {
+                self.integral[0] += self.ki*ex*dt;
+                self.integral[1] += self.ki*ey*dt;
+                self.integral[2] += self.ki*ez*dt;
+            }
+
+            gx += self.kp*ex + self.integral[0];
+            gy += self.kp*ey + self.integral[1];
+            gz += self.kp*ez + self.integral[2];
+        }
+
+        // Integrate the rate of change of the quaternion
+        let half_dt: f32 = 0.5*dt;
+        self.quaternion = Quaternion {
+            w: q.w + (-q.x*gx - q.y*gy - q.z*gz)*half_dt,
+            x: q.x + (q.w*gx + q.y*gz - q.z*gy)*half_dt,
+            y: q.y + (q.w*gy - q.x*gz + q.z*gx)*half_dt,
+            z: q.z + (q.w*gz + q.x*gy - q.y*gx)*half_dt,
+        }.normalize();
+    }
+
+    /// Returns the current attitude estimate as a quaternion.
+    pub fn quaternion(&self) -> Quaternion<f32> {
+        self.quaternion
+    }
+
+    /// Returns the current attitude estimate as roll, pitch, and yaw angles (in minutes of arc).
+    pub fn angles(&self) -> Angles {
+        to_angles(&self.quaternion)
+    }
+}
@@ -0,0 +1,43 @@
+//! Attitude and heading reference system (AHRS) for the Phoenix flight controller.
+//! This module fuses gyroscope and accelerometer samples from the MPU6050 (and, optionally, magnetometer samples) into an attitude estimate.
+//!
+//! Two estimators are available:
+//! - `Mahony`: a complementary filter with proportional-integral gyroscope drift correction
+//! - `Madgwick`: a gradient-descent filter that can also use a magnetometer for drift-free heading
+//!
+//! The `update` methods assume that exactly one nominal timestep has passed since the previous call,
+//! and the `_dt` methods take the measured interval (in microseconds) instead.
+
+use math::Quaternion;
+
+use crate::imu::{
+    Angles,
+    TO_DEGREES,
+};
+
+mod mahony;
+pub use mahony::Mahony;
+mod madgwick;
+pub use madgwick::Madgwick;
+
+
+/// Used for converting degrees into radians.
+const TO_RADIANS: f32 = 1.0 / TO_DEGREES;
+
+
+/// Converts a unit quaternion into roll, pitch, and yaw angles (in minutes of arc).
+///
+/// The angles follow the same axis convention as `Mpu6050::read_angles`:
+/// roll is the rotation about the sensor Y axis, pitch is the rotation about the negative sensor X axis,
+/// and yaw is the rotation about the sensor Z axis.
+/// A rate loop must therefore use the gyroscope Y rate for roll, the negated X rate for pitch, and the Z rate for yaw.
+pub fn to_angles(quaternion: &Quaternion<f32>) -> Angles {
+    let (roll_x, pitch_y, yaw_z) = quaternion.to_euler();
+
+    // Convert first into degrees and then into minutes
+    Angles {
+        pitch: -roll_x*TO_DEGREES*60.0,
+        roll: pitch_y*TO_DEGREES*60.0,
+        yaw: yaw_z*TO_DEGREES*60.0,
+    }
+}
@@ -0,0 +1,285 @@
+//! Arming state machine and ESC endpoint calibration.
+
+use core::convert::TryFrom;
+
+use embedded_hal::PwmPin;
+use math::Real;
+use sys::Status;
+
+use crate::imu::Angles;
+use super::Esc;
+
+
+/// Default throttle (between 0 and 1) at or below which the throttle counts as low.
+const DEFAULT_THROTTLE_LOW: f32 = 0.05;
+
+/// Default largest roll or pitch (in minutes of arc) at which the craft counts as level.
+const DEFAULT_MAX_TILT: f32 = 25.0*60.0;
+
+/// Default time (in microseconds) that the throttle may stay low before the motors disarm.
+const DEFAULT_IDLE_TIMEOUT: u32 = 15_000_000;
+
+/// Default time (in microseconds) that each calibration endpoint is held.
+const DEFAULT_CALIBRATION_TIME: u32 = 5_000_000;
+
+
+/// Enumerates the states of the motors.
+///
+/// # Options
+/// `Disarmed`: Every ESC outputs the stop pulse.
+/// `Armed`: The ESCs follow the motor outputs.
+/// `CalibratingHigh`: Every ESC outputs the full-throttle pulse (first calibration endpoint).
+/// `CalibratingLow`: Every ESC outputs the stop pulse (second calibration endpoint).
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ArmingState {
+    Disarmed,
+    Armed,
+    CalibratingHigh,
+    CalibratingLow,
+}
+
+
+/// Enumerates the reasons why the motors refused to arm or calibrate.
+///
+/// # Options
+/// `Unhealthy`: One or more subsystems are not operational.
+/// `ThrottleHigh`: The throttle is not low.
+/// `NotLevel`: The craft is tilted too far.
+/// `Busy`: The motors are armed or being calibrated.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ArmingError {
+    Unhealthy,
+    ThrottleHigh,
+    NotLevel,
+    Busy,
+}
+
+
+/// Confirms that ESC calibration may run.
+///
+/// Calibration drives every motor to full throttle, so it must never start by accident.
+/// This token can only be constructed by calling `propellers_removed`, which makes the confirmation explicit at the call site.
+pub struct CalibrationConfirmation {
+    _private: (),
+}
+
+impl CalibrationConfirmation {
+    /// Confirms that the propellers have been removed.
+    pub fn propellers_removed() -> Self {
+        CalibrationConfirmation {
+            _private: (),
+        }
+    }
+}
+
+
+/// Defines a set of ESCs guarded by an arming state machine.
+///
+/// The motors start disarmed and only arm if the throttle is low, the craft is level, and every subsystem is healthy.
+/// While armed, they disarm automatically once the throttle has stayed low for the idle timeout.
+///
+/// ESC endpoint calibration holds the full-throttle pulse and then the stop pulse, each for the calibration time.
+/// Start calibration with the ESCs unpowered and power them during the full-throttle endpoint.
+///
+/// # Examples
+/// ```
+/// use api::esc::{ArmingError, ArmingState, CalibrationConfirmation, Esc, Motors, Protocol};
+/// use api::esc::mock::MockPwm;
+/// use api::imu::Angles;
+/// use sys::{Status, StatusCode};
+///
+/// let esc = || Esc::new(MockPwm::new(), Protocol::Pwm, 20_000);
+/// let mut motors = Motors::new([esc(), esc()]);
+/// motors.set_idle_timeout(1_000_000);
+///
+/// let level = Angles { pitch: 0.0, roll: 0.0, yaw: 0.0 };
+/// let status = Status::new();
+/// assert_eq!(motors.arm(0.5, &level, &status), Err(ArmingError::ThrottleHigh));
+/// assert_eq!(motors.arm(0.0, &Angles { pitch: 3000.0, roll: 0.0, yaw: 0.0 }, &status), Err(ArmingError::NotLevel));
+/// assert_eq!(motors.arm(0.0, &level, &status), Ok(()));
+///
+/// motors.update(0.5, &[0.5, 0.5], 1000);
+/// assert_eq!(motors.escs()[0].pulse(), 1525.0);
+///
+/// // One second at low throttle disarms the motors
+/// for _ in 0..1000 {
+///     motors.update(0.0, &[0.0, 0.0], 1000);
+/// }
+/// assert_eq!(motors.state(), ArmingState::Disarmed);
+/// assert_eq!(motors.escs()[0].pulse(), 1000.0);
+///
+/// // Calibration is refused while a subsystem is unhealthy
+/// let mut unhealthy = Status::new();
+/// unhealthy.set_power(StatusCode::Critical);
+/// assert_eq!(
+///     motors.start_calibration(CalibrationConfirmation::propellers_removed(), &unhealthy),
+///     Err(ArmingError::Unhealthy),
+/// );
+///
+/// // Calibration runs through both endpoints and ends disarmed
+/// motors.start_calibration(CalibrationConfirmation::propellers_removed(), &status).unwrap();
+/// motors.update(0.0, &[0.0, 0.0], 1000);
+/// assert_eq!(motors.escs()[1].pulse(), 2000.0);
+/// for _ in 0..10 {
+///     motors.update(0.0, &[0.0, 0.0], 1_000_000);
+/// }
+/// assert_eq!(motors.state(), ArmingState::Disarmed);
+/// assert_eq!(motors.escs()[1].pulse(), 1000.0);
+/// ```
+pub struct Motors<P, const N: usize> {
+    escs: [Esc<P>; N],
+    state: ArmingState,
+    throttle_low: f32,
+    max_tilt: f32,
+    idle_timeout: u32,
+    calibration_time: u32,
+    elapsed: u32,
+}
+
+impl<P, const N: usize> Motors<P, N> where P: PwmPin, P::Duty: Into<u32> + TryFrom<u32> {
+    /// Constructs a new, disarmed set of motors.
+    pub fn new(escs: [Esc<P>; N]) -> Self {
+        let mut motors = Motors {
+            escs: escs,
+            state: ArmingState::Disarmed,
+            throttle_low: DEFAULT_THROTTLE_LOW,
+            max_tilt: DEFAULT_MAX_TILT,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            calibration_time: DEFAULT_CALIBRATION_TIME,
+            elapsed: 0,
+        };
+        motors.disarm();
+        motors
+    }
+
+    /// Sets the throttle (between 0 and 1) at or below which the throttle counts as low.
+    pub fn set_throttle_low(&mut self, throttle: f32) {
+        self.throttle_low = throttle;
+    }
+
+    /// Sets the largest roll or pitch (in minutes of arc) at which the motors may arm.
+    pub fn set_max_tilt(&mut self, tilt: f32) {
+        self.max_tilt = tilt;
+    }
+
+    /// Sets the time (in microseconds) that the throttle may stay low before the motors disarm.
+    /// A timeout of zero never disarms automatically.
+    pub fn set_idle_timeout(&mut self, timeout: u32) {
+        self.idle_timeout = timeout;
+    }
+
+    /// Sets the time (in microseconds) that each calibration endpoint is held.
+    pub fn set_calibration_time(&mut self, time: u32) {
+        self.calibration_time = time;
+    }
+
+    /// Returns the current state of the motors.
+    pub fn state(&self) -> ArmingState {
+        self.state
+    }
+
+    /// Returns true if the motors are armed.
+    pub fn is_armed(&self) -> bool {
+        self.state == ArmingState::Armed
+    }
+
+    /// Returns the ESCs.
+    pub fn escs(&self) -> &[Esc<P>; N] {
+        &self.escs
+    }
+
+    /// Arms the motors if the throttle (between 0 and 1) is low, the craft is level, and every subsystem is healthy.
+    pub fn arm(&mut self, throttle: f32, angles: &Angles, status: &Status) -> Result<(), ArmingError> {
+        match self.state {
+            ArmingState::Armed => return Ok(()),
+            ArmingState::CalibratingHigh | ArmingState::CalibratingLow => return Err(ArmingError::Busy),
+            ArmingState::Disarmed => (),
+        }
+
+        if !status.is_healthy() {
+            return Err(ArmingError::Unhealthy);
+        }
+        if throttle > self.throttle_low {
+            return Err(ArmingError::ThrottleHigh);
+        }
+        if Real::abs(angles.roll) > self.max_tilt || Real::abs(angles.pitch) > self.max_tilt {
+            return Err(ArmingError::NotLevel);
+        }
+
+        for esc in self.escs.iter_mut() {
+            esc.arm();
+        }
+        self.state = ArmingState::Armed;
+        self.elapsed = 0;
+        Ok(())
+    }
+
+    /// Disarms the motors (cancelling any calibration) and outputs the stop pulse.
+    pub fn disarm(&mut self) {
+        for esc in self.escs.iter_mut() {
+            esc.disarm();
+        }
+        self.state = ArmingState::Disarmed;
+        self.elapsed = 0;
+    }
+
+    /// Starts ESC endpoint calibration.
+    /// The motors must be disarmed and every subsystem must be healthy.
+    pub fn start_calibration(&mut self, confirmation: CalibrationConfirmation, status: &Status) -> Result<(), ArmingError> {
+        let _ = confirmation;
+        if self.state != ArmingState::Disarmed {
+            return Err(ArmingError::Busy);
+        }
+        if !status.is_healthy() {
+            return Err(ArmingError::Unhealthy);
+        }
+
+        self.state = ArmingState::CalibratingHigh;
+        self.elapsed = 0;
+        Ok(())
+    }
+
+    /// Updates the motors using the pilot throttle (between 0 and 1), the motor outputs (between 0 and 1, one per ESC),
+    /// and the interval `dt` (in microseconds) since the previous call.
+    /// The motor outputs are only used while armed.
+    pub fn update(&mut self, throttle: f32, outputs: &[f32; N], dt: u32) {
+        match self.state {
+            ArmingState::Disarmed => {
+                for esc in self.escs.iter_mut() {
+                    esc.set_throttle(0.0);
+                }
+            },
+            ArmingState::Armed => {
+                if throttle <= self.throttle_low {
+                    self.elapsed = self.elapsed.saturating_add(dt);
+                    if self.idle_timeout > 0 && self.elapsed >= self.idle_timeout {
+                        self.disarm();
+                        return;
+                    }
+                } else {
+                    self.elapsed = 0;
+                }
+
+                for (esc, output) in self.escs.iter_mut().zip(outputs.iter()) {
+                    esc.set_throttle(*output);
+                }
+            },
+            ArmingState::CalibratingHigh | ArmingState::CalibratingLow => {
+                let high: bool = self.state == ArmingState::CalibratingHigh;
+                for esc in self.escs.iter_mut() {
+                    esc.write_output(if high { 1.0 } else { 0.0 });
+                }
+
+                self.elapsed = self.elapsed.saturating_add(dt);
+                if self.elapsed >= self.calibration_time {
+                    if high {
+                        self.state = ArmingState::CalibratingLow;
+                        self.elapsed = 0;
+                    } else {
+                        self.disarm();
+                    }
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! DShot digital ESC protocol encoder.
+//!
+//! A DShot frame is 16 bits long, sent most significant bit first:
+//! an 11-bit value, a telemetry request bit, and a 4-bit checksum.
+//! Values 1 to 47 are special commands, 0 stops the motor, and 48 to 2047 are throttle.
+//!
+//! Every bit takes the same time, and its value is carried by how long the line stays high.
+//! This module only builds frames and timings, so it does not depend on how the pulses are produced.
+
+use math::Real;
+
+
+/// Lowest frame value that commands throttle.
+pub const MIN_THROTTLE_VALUE: u16 = 48;
+
+/// Highest frame value (full throttle).
+pub const MAX_THROTTLE_VALUE: u16 = 2047;
+
+/// Number of bits in one frame.
+pub const FRAME_BITS: usize = 16;
+
+
+/// Enumerates the special commands understood by DShot ESCs.
+/// Commands that change settings are only accepted while the motors are stopped,
+/// and only after being repeated `repeats()` times with the telemetry bit set.
+///
+/// # Options
+/// `MotorStop`: Stop the motor
+/// `Beep(n)`: Beep tone `n` (1 to 5)
+/// `EscInfo`: Request ESC information over telemetry
+/// `SpinDirection1`: Spin in direction 1
+/// `SpinDirection2`: Spin in direction 2
+/// `Mode3dOff`: Disable 3D (reversible) mode
+/// `Mode3dOn`: Enable 3D (reversible) mode
+/// `SaveSettings`: Save the settings to the ESC memory
+/// `SpinDirectionNormal`: Spin in the normal direction
+/// `SpinDirectionReversed`: Spin in the reversed direction
+#[derive(Copy, Clone, PartialEq)]
+pub enum Command {
+    MotorStop,
+    Beep(u8),
+    EscInfo,
+    SpinDirection1,
+    SpinDirection2,
+    Mode3dOff,
+    Mode3dOn,
+    SaveSettings,
+    SpinDirectionNormal,
+    SpinDirectionReversed,
+}
+
+impl Command {
+    /// Returns the frame value of the command.
+    /// Beep tones outside 1 to 5 are limited to that range.
+    pub fn value(&self) -> u16 {
+        match self {
+            Command::MotorStop => 0,
+            Command::Beep(tone) => (*tone).clamp(1, 5) as u16,
+            Command::EscInfo => 6,
+            Command::SpinDirection1 => 7,
+            Command::SpinDirection2 => 8,
+            Command::Mode3dOff => 9,
+            Command::Mode3dOn => 10,
+            Command::SaveSettings => 12,
+            Command::SpinDirectionNormal => 20,
+            Command::SpinDirectionReversed => 21,
+        }
+    }
+
+    /// Returns the number of consecutive frames the command must be sent in before the ESC acts on it.
+    pub fn repeats(&self) -> u8 {
+        match self {
+            Command::MotorStop | Command::Beep(_) | Command::EscInfo => 1,
+            _ => 6,
+        }
+    }
+}
+
+
+/// Holds one DShot frame.
+///
+/// # Examples
+/// ```
+/// use api::esc::dshot::{Command, Frame, Speed};
+///
+/// // A value of 1046 without telemetry has a checksum of 6
+/// let frame = Frame::new(1046, false);
+/// assert_eq!(frame.bits(), 0b1000_0010_1100_0110);
+/// assert!(Frame::from_bits(frame.bits()).is_some());
+/// assert!(Frame::from_bits(frame.bits() ^ 1).is_none());
+///
+/// assert_eq!(Frame::throttle(0.0, false).value(), 48);
+/// assert_eq!(Frame::throttle(1.0, false).value(), 2047);
+/// assert_eq!(Frame::command(Command::SaveSettings).value(), 12);
+/// assert!(Frame::command(Command::SaveSettings).telemetry());
+///
+/// // At DShot300 on a 16 MHz clock, a bit takes 53 cycles; ones stay high for 40 and zeros for 20
+/// let timing = Speed::Dshot300.timing().in_cycles(16_000_000);
+/// let high = frame.high_times(&timing);
+/// assert_eq!(timing.period, 53);
+/// assert_eq!(&high[..4], &[40, 20, 20, 20]);
+/// ```
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Frame(u16);
+
+impl Frame {
+    /// Constructs a new frame from an 11-bit value (higher bits are ignored) and the telemetry request bit.
+    pub fn new(value: u16, telemetry: bool) -> Self {
+        let packet: u16 = ((value & MAX_THROTTLE_VALUE) << 1) | (telemetry as u16);
+        Frame((packet << 4) | checksum(packet))
+    }
+
+    /// Constructs a new frame commanding a throttle between 0 and 1.
+    pub fn throttle(throttle: f32, telemetry: bool) -> Self {
+        let throttle: f32 = Real::clamp(throttle, 0.0, 1.0);
+        let span: f32 = (MAX_THROTTLE_VALUE - MIN_THROTTLE_VALUE) as f32;
+        Frame::new(MIN_THROTTLE_VALUE + (throttle*span + 0.5) as u16, telemetry)
+    }
+
+    /// Constructs a new frame carrying a special command.
+    /// The telemetry bit is set for commands that must be repeated, as the ESCs require.
+    pub fn command(command: Command) -> Self {
+        Frame::new(command.value(), command.repeats() > 1)
+    }
+
+    /// Constructs a frame from its 16 bits, or returns `None` if the checksum does not match.
+    pub fn from_bits(bits: u16) -> Option<Self> {
+        if checksum(bits >> 4) == bits & 0x0F {
+            Some(Frame(bits))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the 16 bits of the frame, to be sent most significant bit first.
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    /// Returns the 11-bit value of the frame.
+    pub fn value(&self) -> u16 {
+        self.0 >> 5
+    }
+
+    /// Returns true if the frame requests telemetry.
+    pub fn telemetry(&self) -> bool {
+        self.0 & 0x10 != 0
+    }
+
+    /// Returns how long the line stays high for each bit of the frame (most significant bit first),
+    /// in the same units as `timing`.
+    pub fn high_times(&self, timing: &BitTiming) -> [u16; FRAME_BITS] {
+        let mut high: [u16; FRAME_BITS] = [0; FRAME_BITS];
+        for (i, time) in high.iter_mut().enumerate() {
+            let bit: bool = (self.0 >> (FRAME_BITS - 1 - i)) & 1 != 0;
+            *time = if bit { timing.one_high } else { timing.zero_high };
+        }
+        high
+    }
+}
+
+/// Returns the 4-bit checksum of a 12-bit packet (the value and the telemetry bit).
+fn checksum(packet: u16) -> u16 {
+    (packet ^ (packet >> 4) ^ (packet >> 8)) & 0x0F
+}
+
+
+/// Holds the timing of one DShot bit.
+///
+/// # Fields
+/// `period`: Duration of every bit
+/// `one_high`: Time the line stays high for a one
+/// `zero_high`: Time the line stays high for a zero
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct BitTiming {
+    pub period: u16,
+    pub one_high: u16,
+    pub zero_high: u16,
+}
+
+impl BitTiming {
+    /// Converts a timing in nanoseconds into clock cycles at `clock` Hz (rounded to the nearest cycle).
+    pub fn in_cycles(&self, clock: u32) -> BitTiming {
+        let cycles = |nanoseconds: u16| -> u16 {
+            (((nanoseconds as u64)*(clock as u64) + 500_000_000)/1_000_000_000) as u16
+        };
+
+        BitTiming {
+            period: cycles(self.period),
+            one_high: cycles(self.one_high),
+            zero_high: cycles(self.zero_high),
+        }
+    }
+}
+
+
+/// Enumerates the DShot bit rates.
+///
+/// # Options
+/// `Dshot150`: 150 kbit/s
+/// `Dshot300`: 300 kbit/s
+/// `Dshot600`: 600 kbit/s
+#[derive(Copy, Clone, PartialEq)]
+pub enum Speed {
+    Dshot150,
+    Dshot300,
+    Dshot600,
+}
+
+impl Speed {
+    /// Returns the bit timing (in nanoseconds).
+    /// A one stays high for three quarters of the bit and a zero for three eighths.
+    pub fn timing(&self) -> BitTiming {
+        match self {
+            Speed::Dshot150 => BitTiming { period: 6_667, one_high: 5_000, zero_high: 2_500 },
+            Speed::Dshot300 => BitTiming { period: 3_333, one_high: 2_500, zero_high: 1_250 },
+            Speed::Dshot600 => BitTiming { period: 1_667, one_high: 1_250, zero_high: 625 },
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Electronic speed controller (ESC) output driver for the Phoenix flight controller.
+//! Each ESC is driven by one PWM channel, using either standard servo PWM or Oneshot125 pulses.
+//! Frames for digital (DShot) ESCs are built by the `dshot` module.
+
+use core::convert::TryFrom;
+
+use embedded_hal::PwmPin;
+use math::Real;
+
+pub mod dshot;
+mod arming;
+pub use arming::{
+    Motors,
+    ArmingError,
+    ArmingState,
+    CalibrationConfirmation,
+};
+
+
+/// Default lowest output while armed (as a fraction of the protocol range).
+const DEFAULT_MIN: f32 = 0.0;
+
+/// Default output at zero throttle while armed (as a fraction of the protocol range).
+const DEFAULT_IDLE: f32 = 0.05;
+
+/// Default highest output (as a fraction of the protocol range).
+const DEFAULT_MAX: f32 = 1.0;
+
+
+/// Enumerates the pulse protocols understood by the ESCs.
+///
+/// # Options
+/// `Pwm`: Standard servo PWM, with pulses from 1000 µs (stop) to 2000 µs (full throttle).
+/// `Oneshot125`: Oneshot125, with pulses from 125 µs (stop) to 250 µs (full throttle).
+#[derive(Copy, Clone, PartialEq)]
+pub enum Protocol {
+    Pwm,
+    Oneshot125,
+}
+
+impl Protocol {
+    /// Returns the pulse width (in microseconds) that stops the motor.
+    pub fn min_pulse(&self) -> f32 {
+        match self {
+            Protocol::Pwm => 1000.0,
+            Protocol::Oneshot125 => 125.0,
+        }
+    }
+
+    /// Returns the pulse width (in microseconds) that commands full throttle.
+    pub fn max_pulse(&self) -> f32 {
+        match self {
+            Protocol::Pwm => 2000.0,
+            Protocol::Oneshot125 => 250.0,
+        }
+    }
+}
+
+
+/// Defines one ESC driven by a PWM channel.
+///
+/// The ESC starts disarmed.  While disarmed, it always outputs the stop pulse, whatever throttle is requested.
+/// While armed, a throttle between 0 and 1 is mapped onto the range from the idle output to the maximum output,
+/// and the result is kept between the minimum and maximum outputs.
+/// Outputs are fractions of the protocol range, so 0 is the stop pulse and 1 is the full-throttle pulse.
+///
+/// # Examples
+/// ```
+/// use api::esc::{Esc, Protocol};
+/// use api::esc::mock::MockPwm;
+///
+/// // 50 Hz servo PWM, so each duty step is 0.5 µs
+/// let mut esc = Esc::new(MockPwm::new(), Protocol::Pwm, 20_000);
+/// assert_eq!(esc.pulse(), 1000.0);
+///
+/// // Disarmed ESCs ignore the throttle
+/// esc.set_throttle(1.0);
+/// assert_eq!(esc.pulse(), 1000.0);
+///
+/// esc.set_limits(0.05, 0.1, 0.9);
+/// esc.arm();
+/// esc.set_throttle(0.0);
+/// assert_eq!(esc.pulse(), 1100.0);
+/// esc.set_throttle(0.5);
+/// assert_eq!(esc.pulse(), 1500.0);
+/// esc.set_throttle(2.0);
+/// assert_eq!(esc.pulse(), 1900.0);
+///
+/// esc.disarm();
+/// assert_eq!(esc.pulse(), 1000.0);
+/// assert!(esc.release().enabled);
+/// ```
+pub struct Esc<P> {
+    pin: P,
+    protocol: Protocol,
+    period: u32,
+    min: f32,
+    idle: f32,
+    max: f32,
+    armed: bool,
+}
+
+impl<P> Esc<P> where P: PwmPin, P::Duty: Into<u32> + TryFrom<u32> {
+    /// Constructs a new, disarmed ESC on a PWM channel whose period is `period` microseconds.
+    /// The channel is enabled and immediately outputs the stop pulse.
+    pub fn new(pin: P, protocol: Protocol, period: u32) -> Self {
+        let mut esc = Esc {
+            pin: pin,
+            protocol: protocol,
+            period: period,
+            min: DEFAULT_MIN,
+            idle: DEFAULT_IDLE,
+            max: DEFAULT_MAX,
+            armed: false,
+        };
+        esc.pin.enable();
+        esc.stop();
+        esc
+    }
+
+    /// Sets the lowest output while armed, the output at zero throttle, and the highest output
+    /// (each as a fraction of the protocol range, between 0 and 1).
+    pub fn set_limits(&mut self, min: f32, idle: f32, max: f32) {
+        self.min = min;
+        self.idle = idle;
+        self.max = max;
+    }
+
+    /// Arms the ESC, so that it follows the requested throttle.
+    /// The output stays at the stop pulse until the next call to `set_throttle`.
+    pub fn arm(&mut self) {
+        self.armed = true;
+    }
+
+    /// Disarms the ESC and outputs the stop pulse.
+    pub fn disarm(&mut self) {
+        self.armed = false;
+        self.stop();
+    }
+
+    /// Returns true if the ESC is armed.
+    pub fn is_armed(&self) -> bool {
+        self.armed
+    }
+
+    /// Requests a throttle between 0 and 1.
+    /// While disarmed, the stop pulse is output instead.
+    pub fn set_throttle(&mut self, throttle: f32) {
+        if !self.armed {
+            self.stop();
+            return;
+        }
+
+        let throttle: f32 = Real::clamp(throttle, 0.0, 1.0);
+        let output: f32 = Real::clamp(self.idle + throttle*(self.max - self.idle), self.min, self.max);
+        let pulse: f32 = self.protocol.min_pulse() + output*(self.protocol.max_pulse() - self.protocol.min_pulse());
+        self.write_pulse(pulse);
+    }
+
+    /// Outputs a fraction of the protocol range (between 0 and 1), ignoring the arming state and limits.
+    /// This is only used for ESC calibration.
+    pub(crate) fn write_output(&mut self, output: f32) {
+        let output: f32 = Real::clamp(output, 0.0, 1.0);
+        self.write_pulse(self.protocol.min_pulse() + output*(self.protocol.max_pulse() - self.protocol.min_pulse()));
+    }
+
+    /// Outputs the stop pulse.
+    fn stop(&mut self) {
+        self.write_pulse(self.protocol.min_pulse());
+    }
+
+    /// Outputs a pulse of the given width (in microseconds).
+    fn write_pulse(&mut self, pulse: f32) {
+        let max_duty: u32 = self.pin.get_max_duty().into();
+        let duty: u32 = ((pulse/(self.period as f32))*(max_duty as f32) + 0.5) as u32;
+        let duty: u32 = if duty > max_duty { max_duty } else { duty };
+        if let Ok(duty) = P::Duty::try_from(duty) {
+            self.pin.set_duty(duty);
+        }
+    }
+
+    /// Returns the width (in microseconds) of the pulse currently being output.
+    pub fn pulse(&self) -> f32 {
+        let duty: u32 = self.pin.get_duty().into();
+        let max_duty: u32 = self.pin.get_max_duty().into();
+        if max_duty == 0 {
+            0.0
+        } else {
+            (duty as f32)*(self.period as f32)/(max_duty as f32)
+        }
+    }
+
+    /// Returns the protocol used by the ESC.
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    /// Disarms the ESC and releases the PWM channel.
+    /// The channel keeps outputting the stop pulse.
+    pub fn release(mut self) -> P {
+        self.disarm();
+        self.pin
+    }
+}
+
+
+/// Stand-in PWM channel for the examples, so that they run without hardware.
+#[doc(hidden)]
+pub mod mock {
+    use embedded_hal::PwmPin;
+
+    /// Records the duty cycle of a 16-bit PWM channel with a maximum duty of 40000.
+    pub struct MockPwm {
+        pub duty: u16,
+        pub enabled: bool,
+    }
+
+    impl MockPwm {
+        /// Constructs a new disabled channel with a duty of zero.
+        pub fn new() -> Self {
+            MockPwm {
+                duty: 0,
+                enabled: false,
+            }
+        }
+    }
+
+    impl Default for MockPwm {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl PwmPin for MockPwm {
+        type Duty = u16;
+
+        fn disable(&mut self) {
+            self.enabled = false;
+        }
+
+        fn enable(&mut self) {
+            self.enabled = true;
+        }
+
+        fn get_duty(&self) -> u16 {
+            self.duty
+        }
+
+        fn get_max_duty(&self) -> u16 {
+            40_000
+        }
+
+        fn set_duty(&mut self, duty: u16) {
+            self.duty = duty;
+        }
+    }
+}
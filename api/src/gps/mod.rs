@@ -1,5 +1,7 @@
 //! API for the onboard Global Position System module.
 
+use math::Vector3;
+
 /// Holds position data (for a UAS or its control station).
 ///
 /// # Fields
@@ -23,4 +25,10 @@ pub struct Velocity {
     pub x: f32,
     pub y: f32,
     pub z: f32,
+}
+
+impl From<Velocity> for Vector3<f32> {
+    fn from(velocity: Velocity) -> Self {
+        Vector3::new(velocity.x, velocity.y, velocity.z)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,125 @@
+//! Bias offsets computed by the MPU6050 boot-time calibration.
+
+use super::{
+    Accel,
+    Gyro,
+};
+
+
+/// Largest spread (in units of standard gravity) allowed on any accelerometer axis during calibration.
+pub(crate) const ACCEL_MOTION_THRESHOLD: f32 = 0.05;
+
+/// Largest spread (in degrees per second) allowed on any gyroscope axis during calibration.
+pub(crate) const GYRO_MOTION_THRESHOLD: f32 = 2.0;
+
+/// Number of bytes in a serialized set of `Offsets`.
+pub const OFFSETS_BYTES: usize = 24;
+
+
+/// Holds the accelerometer and gyroscope bias offsets of an MPU6050.
+/// These offsets are subtracted from every subsequent reading.
+///
+/// # Fields
+/// `accel`: X, Y, and Z accelerometer biases (in units of standard gravity)
+/// `gyro`: X, Y, and Z gyroscope biases (in degrees per second)
+#[derive(Copy, Clone)]
+pub struct Offsets {
+    pub accel: Accel,
+    pub gyro: Gyro,
+}
+
+impl Offsets {
+    /// Constructs a set of offsets that leaves readings unchanged.
+    pub fn zero() -> Self {
+        Offsets {
+            accel: Accel {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            gyro: Gyro {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+        }
+    }
+
+    /// Serializes the offsets into bytes so that they can be stored (for example, in EEPROM).
+    /// The offsets are written as little-endian `f32` values in the order accel X, Y, Z then gyro X, Y, Z.
+    pub fn to_bytes(&self) -> [u8; OFFSETS_BYTES] {
+        let values: [f32; 6] = [
+            self.accel.x, self.accel.y, self.accel.z,
+            self.gyro.x, self.gyro.y, self.gyro.z,
+        ];
+
+        let mut bytes: [u8; OFFSETS_BYTES] = [0; OFFSETS_BYTES];
+        for (i, value) in values.iter().enumerate() {
+            bytes[4*i..4*i + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Deserializes offsets previously produced by `Offsets::to_bytes`.
+    pub fn from_bytes(bytes: &[u8; OFFSETS_BYTES]) -> Self {
+        let mut values: [f32; 6] = [0.0; 6];
+        for (i, value) in values.iter_mut().enumerate() {
+            let mut word: [u8; 4] = [0; 4];
+            word.copy_from_slice(&bytes[4*i..4*i + 4]);
+            *value = f32::from_le_bytes(word);
+        }
+
+        Offsets {
+            accel: Accel {
+                x: values[0],
+                y: values[1],
+                z: values[2],
+            },
+            gyro: Gyro {
+                x: values[3],
+                y: values[4],
+                z: values[5],
+            },
+        }
+    }
+}
+
+
+/// Tracks the running sum and spread of one sensor axis during calibration.
+pub(crate) struct AxisStats {
+    sum: f32,
+    min: f32,
+    max: f32,
+}
+
+impl AxisStats {
+    /// Constructs an empty set of statistics.
+    pub(crate) fn new() -> Self {
+        AxisStats {
+            sum: 0.0,
+            min: f32::MAX,
+            max: f32::MIN,
+        }
+    }
+
+    /// Adds a sample to the statistics.
+    pub(crate) fn add(&mut self, value: f32) {
+        self.sum += value;
+        if value < self.min {
+            self.min = value;
+        }
+        if value > self.max {
+            self.max = value;
+        }
+    }
+
+    /// Returns the mean of `samples` samples.
+    pub(crate) fn mean(&self, samples: u16) -> f32 {
+        self.sum/(samples as f32)
+    }
+
+    /// Returns the difference between the largest and smallest samples.
+    pub(crate) fn spread(&self) -> f32 {
+        self.max - self.min
+    }
+}
@@ -0,0 +1,159 @@
+//! Configuration options for the MPU6050 driver.
+
+/// Configuration register on board the MPU6050.
+/// Bits 2:0 of this register select the digital low-pass filter bandwidth.
+pub(crate) const MPU6050_CONFIG_REGISTER: u8 = 0x1A;
+
+/// Sample rate divider register on board the MPU6050.
+/// The sample rate is the gyroscope output rate divided by `1 + SMPLRT_DIV`.
+pub(crate) const MPU6050_SMPLRT_DIV_REGISTER: u8 = 0x19;
+
+/// Gyroscope configuration register on board the MPU6050.
+/// Bits 4:3 of this register select the gyroscope full-scale range.
+pub(crate) const MPU6050_GYRO_CONFIG_REGISTER: u8 = 0x1B;
+
+/// Accelerometer configuration register on board the MPU6050.
+/// Bits 4:3 of this register select the accelerometer full-scale range.
+pub(crate) const MPU6050_ACCEL_CONFIG_REGISTER: u8 = 0x1C;
+
+
+/// Enumerates the accelerometer full-scale ranges supported by the MPU6050.
+///
+/// # Options
+/// `G2`: ±2 g (power-on default)
+/// `G4`: ±4 g
+/// `G8`: ±8 g
+/// `G16`: ±16 g
+#[derive(Copy, Clone, PartialEq)]
+#[repr(u8)]
+pub enum AccelRange {
+    G2 = 0,
+    G4 = 1,
+    G8 = 2,
+    G16 = 3,
+}
+
+impl AccelRange {
+    /// Returns the value to be written to the accelerometer configuration register.
+    pub fn bits(self) -> u8 {
+        (self as u8) << 3
+    }
+
+    /// Returns the accelerometer sensitivity (in LSB per g) for this range.
+    pub fn sensitivity(self) -> f32 {
+        match self {
+            AccelRange::G2 => 16384.0,
+            AccelRange::G4 => 8192.0,
+            AccelRange::G8 => 4096.0,
+            AccelRange::G16 => 2048.0,
+        }
+    }
+}
+
+
+/// Enumerates the gyroscope full-scale ranges supported by the MPU6050.
+///
+/// # Options
+/// `Dps250`: ±250 °/s (power-on default)
+/// `Dps500`: ±500 °/s
+/// `Dps1000`: ±1000 °/s
+/// `Dps2000`: ±2000 °/s
+#[derive(Copy, Clone, PartialEq)]
+#[repr(u8)]
+pub enum GyroRange {
+    Dps250 = 0,
+    Dps500 = 1,
+    Dps1000 = 2,
+    Dps2000 = 3,
+}
+
+impl GyroRange {
+    /// Returns the value to be written to the gyroscope configuration register.
+    pub fn bits(self) -> u8 {
+        (self as u8) << 3
+    }
+
+    /// Returns the gyroscope sensitivity (in LSB per degree per second) for this range.
+    pub fn sensitivity(self) -> f32 {
+        match self {
+            GyroRange::Dps250 => 131.0,
+            GyroRange::Dps500 => 65.5,
+            GyroRange::Dps1000 => 32.8,
+            GyroRange::Dps2000 => 16.4,
+        }
+    }
+}
+
+
+/// Enumerates the digital low-pass filter (DLPF) settings of the MPU6050.
+/// Each option is named for its accelerometer bandwidth; the gyroscope bandwidth is nearly identical.
+///
+/// # Options
+/// `Hz260`: 260 Hz bandwidth, gyroscope output rate of 8 kHz (power-on default)
+/// `Hz184`: 184 Hz bandwidth, gyroscope output rate of 1 kHz
+/// `Hz94`: 94 Hz bandwidth, gyroscope output rate of 1 kHz
+/// `Hz44`: 44 Hz bandwidth, gyroscope output rate of 1 kHz
+/// `Hz21`: 21 Hz bandwidth, gyroscope output rate of 1 kHz
+/// `Hz10`: 10 Hz bandwidth, gyroscope output rate of 1 kHz
+/// `Hz5`: 5 Hz bandwidth, gyroscope output rate of 1 kHz
+#[derive(Copy, Clone, PartialEq)]
+#[repr(u8)]
+pub enum DlpfBandwidth {
+    Hz260 = 0,
+    Hz184 = 1,
+    Hz94 = 2,
+    Hz44 = 3,
+    Hz21 = 4,
+    Hz10 = 5,
+    Hz5 = 6,
+}
+
+impl DlpfBandwidth {
+    /// Returns the value to be written to the configuration register.
+    pub fn bits(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns the gyroscope output rate (in Hz) with this filter setting.
+    pub fn gyro_output_rate(self) -> u16 {
+        match self {
+            DlpfBandwidth::Hz260 => 8000,
+            _ => 1000,
+        }
+    }
+}
+
+
+/// Holds the settings applied to the MPU6050 at initialization.
+///
+/// # Fields
+/// `accel_range`: accelerometer full-scale range
+/// `gyro_range`: gyroscope full-scale range
+/// `dlpf`: digital low-pass filter bandwidth
+/// `sample_rate_divider`: value of SMPLRT_DIV (sample rate = gyroscope output rate / (1 + divider))
+#[derive(Copy, Clone)]
+pub struct Mpu6050Config {
+    pub accel_range: AccelRange,
+    pub gyro_range: GyroRange,
+    pub dlpf: DlpfBandwidth,
+    pub sample_rate_divider: u8,
+}
+
+impl Mpu6050Config {
+    /// Returns the sample rate (in Hz) produced by this configuration.
+    pub fn sample_rate(&self) -> f32 {
+        (self.dlpf.gyro_output_rate() as f32)/(1.0 + self.sample_rate_divider as f32)
+    }
+}
+
+impl Default for Mpu6050Config {
+    /// Returns the MPU6050 power-on configuration.
+    fn default() -> Self {
+        Mpu6050Config {
+            accel_range: AccelRange::G2,
+            gyro_range: GyroRange::Dps250,
+            dlpf: DlpfBandwidth::Hz260,
+            sample_rate_divider: 0,
+        }
+    }
+}
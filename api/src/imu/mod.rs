@@ -1,32 +1,124 @@
 //! Custom MPU6050 driver code for the open-source eternalOS flight control software.
-//! This driver receives acceleration data from the MPU6050 6-axis accelerometer/gyroscope.
+//! This driver receives acceleration, temperature, and angular rate data from the MPU6050 6-axis accelerometer/gyroscope.
 
 use math::{
-    arctan,
+    atan2,
+    Vector3,
+};
+
+mod calibration;
+pub use calibration::{
+    Offsets,
+    OFFSETS_BYTES,
+};
+use calibration::{
+    AxisStats,
+    ACCEL_MOTION_THRESHOLD,
+    GYRO_MOTION_THRESHOLD,
+};
+
+mod config;
+pub use config::{
+    AccelRange,
+    GyroRange,
+    DlpfBandwidth,
+    Mpu6050Config,
+};
+use config::{
+    MPU6050_CONFIG_REGISTER,
+    MPU6050_SMPLRT_DIV_REGISTER,
+    MPU6050_GYRO_CONFIG_REGISTER,
+    MPU6050_ACCEL_CONFIG_REGISTER,
 };
 
 
 /// Defines an abstract object representing an MPU6050 unit.
 pub struct Mpu6050<I> {
     i2c: I,
-    address: u8
+    address: u8,
+    accel_sensitivity: f32,
+    gyro_sensitivity: f32,
+    offsets: Offsets,
 }
 
 /// Defines an enumerated type for different types of errors that the MPU6050 driver could generate.
 pub enum Mpu6050Error<E> {
+    /// An I2C transaction with the MPU6050 failed.
     I2c(E),
-    Accel(E),
+    /// The WHO_AM_I register held this value instead of `MPU6050_WHO_AM_I`, so the device is not an MPU6050.
+    WrongDevice(u8),
+    /// The accelerometer data cannot be converted into angles (for example, every acceleration is zero).
+    InvalidAccel,
+    /// The sensor moved during calibration, so the calibration was rejected.
+    Motion,
 }
 
 
-/// This is the default MPU6050 I2C address.
-const DEFAULT_MPU6050_ADDRESS: u8 = 0x68;
+/// This is the default MPU6050 I2C address (with the AD0 pin pulled low).
+pub const DEFAULT_MPU6050_ADDRESS: u8 = 0x68;
 
-/// Defines a struct that can store the X, Y, and Z accelerations as detected by the MPU6050.
+/// This is the alternate MPU6050 I2C address (with the AD0 pin pulled high).
+pub const ALTERNATE_MPU6050_ADDRESS: u8 = 0x69;
+
+/// Identity register on board the MPU6050.
+const MPU6050_WHO_AM_I_REGISTER: u8 = 0x75;
+
+/// Value held by the identity register of every MPU6050, regardless of the state of the AD0 pin.
+pub const MPU6050_WHO_AM_I: u8 = 0x68;
+
+/// Defines a struct that can store the X, Y, and Z accelerations (in units of standard gravity) as detected by the MPU6050.
+#[derive(Copy, Clone)]
 pub struct Accel {
-    pub x: i16,
-    pub y: i16,
-    pub z: i16,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+/// Defines a struct that can store the X, Y, and Z angular rates (in degrees per second) as detected by the MPU6050.
+#[derive(Copy, Clone)]
+pub struct Gyro {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+/// Defines a struct that can store the X, Y, and Z components of the magnetic field from an external magnetometer.
+/// The MPU6050 has no magnetometer; this is used by `crate::ahrs::Madgwick`, which only needs the field direction, so any units may be used.
+#[derive(Copy, Clone)]
+pub struct Mag {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl From<Accel> for Vector3<f32> {
+    fn from(accel: Accel) -> Self {
+        Vector3::new(accel.x, accel.y, accel.z)
+    }
+}
+
+impl From<Gyro> for Vector3<f32> {
+    fn from(gyro: Gyro) -> Self {
+        Vector3::new(gyro.x, gyro.y, gyro.z)
+    }
+}
+
+impl From<Mag> for Vector3<f32> {
+    fn from(mag: Mag) -> Self {
+        Vector3::new(mag.x, mag.y, mag.z)
+    }
+}
+
+/// Defines a struct that can store a complete motion sample from the MPU6050.
+///
+/// # Fields
+/// `accel`: X, Y, and Z accelerations
+/// `temp`: Die temperature (in degrees Celsius)
+/// `gyro`: X, Y, and Z angular rates
+pub struct Motion {
+    pub accel: Accel,
+    pub temp: f32,
+    pub gyro: Gyro,
 }
 
 /// Reset register on board the MPU6050.
@@ -38,16 +130,31 @@ const MPU6050_RESET_REGISTER: u8 = 0x6B;
 /// See the documentation for `Mpu6050::read_accel` for more information.
 const MPU6050_ACCEL_REGISTER: u8 = 0x3B;
 
+/// Address of the first gyroscope register on the MPU6050.
+/// `0x43` and `0x44` together hold the X angular rate, `0x45` and `0x46` together hold the Y angular rate, and `0x47` and `0x48` together hold the Z angular rate.
+/// See the documentation for `Mpu6050::read_gyro` for more information.
+const MPU6050_GYRO_REGISTER: u8 = 0x43;
+
+/// Number of bytes in a combined accelerometer, temperature, and gyroscope burst read.
+/// The burst starts at `MPU6050_ACCEL_REGISTER` and ends at `0x48` (the last gyroscope register).
+const MPU6050_MOTION_BYTES: usize = 14;
+
+/// Temperature sensitivity (in LSB per degree Celsius) and offset (in degrees Celsius) from the MPU6050 register map.
+const TEMP_SENSITIVITY: f32 = 340.0;
+const TEMP_OFFSET: f32 = 36.53;
+
 
 /// Used for converting radians into degrees.
 const PI: f32 = 3.141592653;
 pub const TO_DEGREES: f32 = 180.0 / PI;
 
 
-/// Defines a struct that can store the pitch and roll values (in minutes of arc) of the MPU6050 with respect to the IMU's default axes.
+/// Defines a struct that can store the pitch, roll, and yaw values (in minutes of arc) of the MPU6050 with respect to the IMU's default axes.
+#[derive(Copy, Clone)]
 pub struct Angles {
     pub pitch: f32,
     pub roll: f32,
+    pub yaw: f32,
 }
 
 
@@ -55,11 +162,21 @@ pub struct Angles {
 impl<I, E> Mpu6050<I>
     where I: embedded_hal::blocking::i2c::WriteRead<Error = E> + embedded_hal::blocking::i2c::Write<Error = E>
 {
-    /// Creates a new instance of the Mpu6050 struct.
+    /// Creates a new instance of the Mpu6050 struct at the default I2C address.
+    /// Readings are scaled for the power-on ranges until `init` or `configure` is called.
     pub fn new(i2c: I) -> Self {
+        Self::new_with_address(i2c, DEFAULT_MPU6050_ADDRESS)
+    }
+
+    /// Creates a new instance of the Mpu6050 struct at the given I2C address.
+    /// This is usually `ALTERNATE_MPU6050_ADDRESS` when the AD0 pin is pulled high.
+    pub fn new_with_address(i2c: I, address: u8) -> Self {
         Mpu6050 {
             i2c: i2c,
-            address: DEFAULT_MPU6050_ADDRESS
+            address: address,
+            accel_sensitivity: AccelRange::G2.sensitivity(),
+            gyro_sensitivity: GyroRange::Dps250.sensitivity(),
+            offsets: Offsets::zero(),
         }
     }
 
@@ -96,49 +213,213 @@ impl<I, E> Mpu6050<I>
         Ok(())
     }
 
-    pub fn wake(&mut self) -> Result<(), Mpu6050Error<E>> {
-        self.i2c.write(self.address, &[MPU6050_RESET_REGISTER, 0x00])
+    /// Writes a single byte to the specified register.
+    pub fn write_byte(&mut self, register: u8, value: u8) -> Result<(), Mpu6050Error<E>> {
+        self.i2c.write(self.address, &[register, value])
             .map_err(Mpu6050Error::I2c)?;
         Ok(())
     }
 
-    /// Read the X, Y, and Z accelerations as measured by the MPU6050.
+    /// Reads the identity (WHO_AM_I) register of the device.
+    pub fn probe(&mut self) -> Result<u8, Mpu6050Error<E>> {
+        let mut buffer: [u8; 1] = [0; 1];
+        self.read_bytes(MPU6050_WHO_AM_I_REGISTER, &mut buffer)?;
+        Ok(buffer[0])
+    }
+
+    /// Confirms that the device at this address is an MPU6050.
+    ///
+    /// # Errors
+    /// This function returns `Mpu6050Error::WrongDevice` if the identity register does not hold `MPU6050_WHO_AM_I`,
+    /// or an `Mpu6050Error::I2c` error if no device answered.
+    pub fn verify(&mut self) -> Result<(), Mpu6050Error<E>> {
+        match self.probe()? {
+            MPU6050_WHO_AM_I => Ok(()),
+            identity => Err(Mpu6050Error::WrongDevice(identity)),
+        }
+    }
+
+    /// Wakes the MPU6050 from sleep mode.
+    pub fn wake(&mut self) -> Result<(), Mpu6050Error<E>> {
+        self.write_byte(MPU6050_RESET_REGISTER, 0x00)
+    }
+
+    /// Writes the full-scale ranges, low-pass filter, and sample rate divider to the MPU6050.
+    /// Subsequent readings are scaled to the configured ranges.
+    pub fn configure(&mut self, config: Mpu6050Config) -> Result<(), Mpu6050Error<E>> {
+        self.write_byte(MPU6050_SMPLRT_DIV_REGISTER, config.sample_rate_divider)?;
+        self.write_byte(MPU6050_CONFIG_REGISTER, config.dlpf.bits())?;
+        self.write_byte(MPU6050_GYRO_CONFIG_REGISTER, config.gyro_range.bits())?;
+        self.write_byte(MPU6050_ACCEL_CONFIG_REGISTER, config.accel_range.bits())?;
+
+        self.accel_sensitivity = config.accel_range.sensitivity();
+        self.gyro_sensitivity = config.gyro_range.sensitivity();
+        Ok(())
+    }
+
+    /// Verifies the identity of the MPU6050, wakes it, and applies the given configuration.
+    pub fn init(&mut self, config: Mpu6050Config) -> Result<(), Mpu6050Error<E>> {
+        self.verify()?;
+        self.wake()?;
+        self.configure(config)
+    }
+
+    /// Read the X, Y, and Z accelerations (in units of standard gravity) as measured by the MPU6050.
     pub fn read_accel(&mut self) -> Result<Accel, Mpu6050Error<E>> {
         let mut buffer: [u8; 6] = [0; 6];
         self.read_bytes(MPU6050_ACCEL_REGISTER, &mut buffer)?;
 
+        Ok(self.unpack_accel(&buffer))
+    }
+
+    /// Read the X, Y, and Z angular rates (in degrees per second) as measured by the MPU6050.
+    pub fn read_gyro(&mut self) -> Result<Gyro, Mpu6050Error<E>> {
+        let mut buffer: [u8; 6] = [0; 6];
+        self.read_bytes(MPU6050_GYRO_REGISTER, &mut buffer)?;
+
+        Ok(self.unpack_gyro(&buffer))
+    }
+
+    /// Read the accelerations, temperature, and angular rates as measured by the MPU6050.
+    ///
+    /// All fourteen bytes are fetched in a single I2C transaction, so the control loop
+    /// should prefer this over calling `read_accel` and `read_gyro` separately.
+    pub fn read_motion(&mut self) -> Result<Motion, Mpu6050Error<E>> {
+        let mut buffer: [u8; MPU6050_MOTION_BYTES] = [0; MPU6050_MOTION_BYTES];
+        self.read_bytes(MPU6050_ACCEL_REGISTER, &mut buffer)?;
+
         // Unpack values
-        let acc_x: i16 = (buffer[0] as i16) << 8 | buffer[1] as i16;
-        let acc_y: i16 = (buffer[2] as i16) << 8 | buffer[3] as i16;
-        let acc_z: i16 = (buffer[4] as i16) << 8 | buffer[5] as i16;
-
-        // Return struct instance
-        let accelerations = Accel {
-            x: acc_x,
-            y: acc_y,
-            z: acc_z
+        let temp: i16 = to_i16(buffer[6], buffer[7]);
+
+        let motion = Motion {
+            accel: self.unpack_accel(&buffer[0..6]),
+            temp: (temp as f32)/TEMP_SENSITIVITY + TEMP_OFFSET,
+            gyro: self.unpack_gyro(&buffer[8..14]),
+        };
+
+        Ok(motion)
+    }
+
+    /// Calibrates the MPU6050 by averaging `samples` readings while the craft sits still and level.
+    ///
+    /// The accelerometer bias is measured relative to 1 g along the +Z axis and the gyroscope bias relative to zero.
+    /// The new offsets are applied to all subsequent reads and returned so that they can be stored with `Offsets::to_bytes`.
+    ///
+    /// # Errors
+    /// This function returns `Mpu6050Error::Motion` (and keeps the previous offsets) if the spread of any axis
+    /// exceeds `ACCEL_MOTION_THRESHOLD` or `GYRO_MOTION_THRESHOLD`, or an `Mpu6050Error::I2c` error if a read fails.
+    pub fn calibrate(&mut self, samples: u16) -> Result<Offsets, Mpu6050Error<E>> {
+        let samples = if samples == 0 { 1 } else { samples };
+
+        // Measure raw readings, without the current offsets applied
+        let previous = self.offsets;
+        self.offsets = Offsets::zero();
+
+        let mut stats: [AxisStats; 6] = [
+            AxisStats::new(), AxisStats::new(), AxisStats::new(),
+            AxisStats::new(), AxisStats::new(), AxisStats::new(),
+        ];
+        for _ in 0..samples {
+            let motion = match self.read_motion() {
+                Ok(motion) => motion,
+                Err(e) => {
+                    self.offsets = previous;
+                    return Err(e);
+                },
+            };
+            stats[0].add(motion.accel.x);
+            stats[1].add(motion.accel.y);
+            stats[2].add(motion.accel.z);
+            stats[3].add(motion.gyro.x);
+            stats[4].add(motion.gyro.y);
+            stats[5].add(motion.gyro.z);
+        }
+
+        // Reject the calibration if the craft was not sitting still
+        let moving = stats[0..3].iter().any(|axis| axis.spread() > ACCEL_MOTION_THRESHOLD)
+            || stats[3..6].iter().any(|axis| axis.spread() > GYRO_MOTION_THRESHOLD);
+        if moving {
+            self.offsets = previous;
+            return Err(Mpu6050Error::Motion);
+        }
+
+        self.offsets = Offsets {
+            accel: Accel {
+                x: stats[0].mean(samples),
+                y: stats[1].mean(samples),
+                z: stats[2].mean(samples) - 1.0,
+            },
+            gyro: Gyro {
+                x: stats[3].mean(samples),
+                y: stats[4].mean(samples),
+                z: stats[5].mean(samples),
+            },
         };
 
-        Ok(accelerations)
+        Ok(self.offsets)
+    }
+
+    /// Returns the bias offsets currently applied to readings.
+    pub fn offsets(&self) -> Offsets {
+        self.offsets
+    }
+
+    /// Applies previously stored bias offsets (for example, restored with `Offsets::from_bytes`).
+    pub fn set_offsets(&mut self, offsets: Offsets) {
+        self.offsets = offsets;
     }
 
     /// Read the roll and pitch angles as measured by the MPU6050.
+    /// The accelerometer cannot observe heading, so `yaw` is always zero; see `crate::ahrs` for a full attitude estimate.
+    ///
+    /// # Errors
+    /// This function returns `Mpu6050Error::InvalidAccel` rather than meaningless angles if every acceleration is zero
+    /// (for example, in free fall).
     pub fn read_angles(&mut self) -> Result<Angles, Mpu6050Error<E>> {
         let accel = self.read_accel()?;
+        if accel.x == 0.0 && accel.y == 0.0 && accel.z == 0.0 {
+            return Err(Mpu6050Error::InvalidAccel);
+        }
 
-        let tan_roll: f32 = (-accel.x as f32)/(accel.z as f32);
-        let roll: f32 = arctan(tan_roll);
-
-        let tan_pitch: f32 = (-accel.y as f32)/(accel.z as f32);
-        let pitch: f32 = arctan(tan_pitch);
+        // `atan2` is valid in all quadrants, so these remain accurate at large tilts
+        let roll: f32 = atan2(-accel.x, accel.z);
+        let pitch: f32 = atan2(-accel.y, accel.z);
 
         // Return the angle values
         // Convert them first into degrees and then into minutes
         let angles = Angles {
             pitch: (pitch*TO_DEGREES*60.0) as f32,
             roll: (roll*TO_DEGREES*60.0) as f32,
+            yaw: 0.0,
         };
         
         Ok(angles)
     }
+
+    /// Converts six bytes of raw accelerometer data into bias-corrected accelerations (in units of standard gravity).
+    fn unpack_accel(&self, buffer: &[u8]) -> Accel {
+        let bias = self.offsets.accel;
+        Accel {
+            x: (to_i16(buffer[0], buffer[1]) as f32)/self.accel_sensitivity - bias.x,
+            y: (to_i16(buffer[2], buffer[3]) as f32)/self.accel_sensitivity - bias.y,
+            z: (to_i16(buffer[4], buffer[5]) as f32)/self.accel_sensitivity - bias.z,
+        }
+    }
+
+    /// Converts six bytes of raw gyroscope data into bias-corrected angular rates (in degrees per second).
+    fn unpack_gyro(&self, buffer: &[u8]) -> Gyro {
+        let bias = self.offsets.gyro;
+        Gyro {
+            x: (to_i16(buffer[0], buffer[1]) as f32)/self.gyro_sensitivity - bias.x,
+            y: (to_i16(buffer[2], buffer[3]) as f32)/self.gyro_sensitivity - bias.y,
+            z: (to_i16(buffer[4], buffer[5]) as f32)/self.gyro_sensitivity - bias.z,
+        }
+    }
+}
+
+
+/// Combines a high byte and a low byte (as stored in the MPU6050 data registers) into a signed 16-bit value.
+#[inline(always)]
+fn to_i16(high: u8, low: u8) -> i16 {
+    (high as i16) << 8 | low as i16
 }
\ No newline at end of file
@@ -3,9 +3,15 @@
 #![no_std]
 
 pub mod imu;
+pub mod ahrs;
 pub mod id;
 pub mod gps;
 pub mod rtc;
 pub mod pid;
+pub mod mixer;
+pub mod esc;
+pub mod pwr;
+pub mod mode;
+pub mod usr;
 
 pub use embedded_nrf24l01 as rf;
\ No newline at end of file
@@ -0,0 +1,187 @@
+//! Motor mixer for the Phoenix flight controller.
+//! This module turns throttle, roll, pitch, and yaw demands into per-motor outputs using a mixing table.
+//!
+//! Presets follow the Betaflight motor numbering and sign convention:
+//! positive roll raises the left motors, positive pitch raises the rear motors,
+//! and positive yaw raises the motors that spin clockwise (seen from above).
+
+use math::Real;
+
+
+/// Largest number of motors supported by a mixer.
+pub const MAX_MOTORS: usize = 8;
+
+
+/// Holds the contribution of each demand to one motor.
+///
+/// # Fields
+/// `throttle`: Throttle factor (normally 1)
+/// `roll`: Roll factor
+/// `pitch`: Pitch factor
+/// `yaw`: Yaw factor
+#[derive(Copy, Clone)]
+pub struct MotorMix {
+    pub throttle: f32,
+    pub roll: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+}
+
+impl MotorMix {
+    /// Constructs a new motor mix from its factors.
+    pub const fn new(throttle: f32, roll: f32, pitch: f32, yaw: f32) -> Self {
+        MotorMix {
+            throttle: throttle,
+            roll: roll,
+            pitch: pitch,
+            yaw: yaw,
+        }
+    }
+}
+
+
+/// Mixing table for a quadcopter in X configuration (rear right, front right, rear left, front left).
+pub const QUAD_X: [MotorMix; 4] = [
+    MotorMix::new(1.0, -1.0, 1.0, -1.0),
+    MotorMix::new(1.0, -1.0, -1.0, 1.0),
+    MotorMix::new(1.0, 1.0, 1.0, 1.0),
+    MotorMix::new(1.0, 1.0, -1.0, -1.0),
+];
+
+/// Mixing table for a quadcopter in + configuration (rear, right, left, front).
+pub const QUAD_PLUS: [MotorMix; 4] = [
+    MotorMix::new(1.0, 0.0, 1.0, -1.0),
+    MotorMix::new(1.0, -1.0, 0.0, 1.0),
+    MotorMix::new(1.0, 1.0, 0.0, 1.0),
+    MotorMix::new(1.0, 0.0, -1.0, -1.0),
+];
+
+/// Mixing table for a hexacopter in X configuration (rear right, front right, rear left, front left, right, left).
+pub const HEX_X: [MotorMix; 6] = [
+    MotorMix::new(1.0, -0.5, 0.866_025, 1.0),
+    MotorMix::new(1.0, -0.5, -0.866_025, 1.0),
+    MotorMix::new(1.0, 0.5, 0.866_025, -1.0),
+    MotorMix::new(1.0, 0.5, -0.866_025, -1.0),
+    MotorMix::new(1.0, -1.0, 0.0, -1.0),
+    MotorMix::new(1.0, 1.0, 0.0, 1.0),
+];
+
+
+/// Defines a motor mixer.
+///
+/// Throttle lies between 0 and 1, and each motor output lies between 0 and 1.
+/// When the attitude demands need more than the full output range, they are scaled down together,
+/// so the craft still rotates in the demanded direction.
+/// With airmode enabled, the throttle is then shifted so that no motor leaves the output range,
+/// which keeps full attitude authority at zero and full throttle.
+/// Without airmode, motors that leave the output range are clipped.
+///
+/// # Examples
+/// ```
+/// use api::mixer::Mixer;
+///
+/// // At zero throttle, airmode raises the throttle just enough to roll
+/// let mut mixer = Mixer::quad_x();
+/// mixer.set_airmode(true);
+/// let outputs = mixer.mix(0.0, 0.2, 0.0, 0.0);
+/// assert_eq!(&outputs[..4], &[0.0, 0.0, 0.4, 0.4]);
+///
+/// // Demands that exceed the output range are scaled down without changing their ratio
+/// let outputs = mixer.mix(0.5, 0.75, 0.25, 0.0);
+/// assert_eq!(&outputs[..4], &[0.25, 0.0, 1.0, 0.75]);
+///
+/// // Without airmode, the motors on the low side are clipped instead
+/// mixer.set_airmode(false);
+/// let outputs = mixer.mix(0.0, 0.2, 0.0, 0.0);
+/// assert_eq!(&outputs[..4], &[0.0, 0.0, 0.2, 0.2]);
+/// ```
+pub struct Mixer {
+    table: [MotorMix; MAX_MOTORS],
+    motors: usize,
+    airmode: bool,
+}
+
+impl Mixer {
+    /// Constructs a new mixer from a custom mixing table (one entry per motor).
+    ///
+    /// # Panics
+    /// This function panics if the table has more than `MAX_MOTORS` entries.
+    pub fn custom(table: &[MotorMix]) -> Self {
+        assert!(table.len() <= MAX_MOTORS);
+
+        let mut mixer = Mixer {
+            table: [MotorMix::new(0.0, 0.0, 0.0, 0.0); MAX_MOTORS],
+            motors: table.len(),
+            airmode: false,
+        };
+        mixer.table[..table.len()].copy_from_slice(table);
+        mixer
+    }
+
+    /// Constructs a new mixer for a quadcopter in X configuration.
+    pub fn quad_x() -> Self {
+        Mixer::custom(&QUAD_X)
+    }
+
+    /// Constructs a new mixer for a quadcopter in + configuration.
+    pub fn quad_plus() -> Self {
+        Mixer::custom(&QUAD_PLUS)
+    }
+
+    /// Constructs a new mixer for a hexacopter in X configuration.
+    pub fn hex_x() -> Self {
+        Mixer::custom(&HEX_X)
+    }
+
+    /// Enables or disables airmode.
+    pub fn set_airmode(&mut self, airmode: bool) {
+        self.airmode = airmode;
+    }
+
+    /// Returns the number of motors driven by the mixer.
+    pub fn motors(&self) -> usize {
+        self.motors
+    }
+
+    /// Mixes the throttle (between 0 and 1) and the roll, pitch, and yaw demands into motor outputs (between 0 and 1).
+    /// Outputs beyond the number of motors are zero.
+    pub fn mix(&self, throttle: f32, roll: f32, pitch: f32, yaw: f32) -> [f32; MAX_MOTORS] {
+        let table = &self.table[..self.motors];
+        let mut outputs: [f32; MAX_MOTORS] = [0.0; MAX_MOTORS];
+
+        // Mix the attitude demands and find their range
+        let mut min: f32 = 0.0;
+        let mut max: f32 = 0.0;
+        for (output, mix) in outputs.iter_mut().zip(table.iter()) {
+            *output = mix.roll*roll + mix.pitch*pitch + mix.yaw*yaw;
+            if *output < min {
+                min = *output;
+            }
+            if *output > max {
+                max = *output;
+            }
+        }
+
+        // Scale the attitude demands down together if they cannot all fit in the output range
+        let range: f32 = max - min;
+        if range > 1.0 {
+            for output in outputs[..self.motors].iter_mut() {
+                *output /= range;
+            }
+            min /= range;
+            max /= range;
+        }
+
+        // Shift the throttle so that every motor stays in range
+        let throttle: f32 = if self.airmode {
+            Real::clamp(throttle, -min, 1.0 - max)
+        } else {
+            throttle
+        };
+
+        for (output, mix) in outputs.iter_mut().zip(table.iter()) {
+            *output = Real::clamp(*output + mix.throttle*throttle, 0.0, 1.0);
+        }
+        outputs
+    }
+}
@@ -0,0 +1,79 @@
+//! Flight mode logic for the Phoenix flight controller.
+//! The flight mode decides which throttle and attitude the controllers are asked to hold.
+
+use crate::imu::Angles;
+
+
+/// Default throttle (between 0 and 1) held during a controlled descent.
+/// This should lie a little below the hover throttle of the craft.
+pub const DEFAULT_DESCENT_THROTTLE: f32 = 0.35;
+
+
+/// Holds the throttle and attitude that the controllers are asked to hold.
+///
+/// # Fields
+/// `throttle`: Throttle (between 0 and 1)
+/// `angles`: Expected angles (in minutes of arc)
+#[derive(Copy, Clone)]
+pub struct Setpoints {
+    pub throttle: f32,
+    pub angles: Angles,
+}
+
+
+/// Enumerates flight modes.
+///
+/// # Options
+/// `Stabilized`: The pilot commands the throttle and attitude.
+/// `Descent`: The craft levels out and descends; the pilot may lower the throttle below the descent throttle but not raise it,
+/// and keeps control of yaw.
+/// `Landed`: Touchdown has been detected; the throttle is cut and the motors should be disarmed.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum FlightMode {
+    Stabilized,
+    Descent,
+    Landed,
+}
+
+impl FlightMode {
+    /// Returns the setpoints to fly in this mode, given the pilot setpoints and the descent throttle.
+    ///
+    /// # Examples
+    /// ```
+    /// use api::imu::Angles;
+    /// use api::mode::{FlightMode, Setpoints, DEFAULT_DESCENT_THROTTLE};
+    ///
+    /// let pilot = |throttle| Setpoints { throttle: throttle, angles: Angles { pitch: 600.0, roll: 0.0, yaw: 90.0 } };
+    ///
+    /// // A descent never raises the throttle, so motors idling on the ground stay idle
+    /// let setpoints = FlightMode::Descent.setpoints(pilot(0.0), DEFAULT_DESCENT_THROTTLE);
+    /// assert_eq!(setpoints.throttle, 0.0);
+    ///
+    /// // In flight, the throttle is capped at the descent throttle and the craft levels out
+    /// let setpoints = FlightMode::Descent.setpoints(pilot(0.6), DEFAULT_DESCENT_THROTTLE);
+    /// assert_eq!(setpoints.throttle, DEFAULT_DESCENT_THROTTLE);
+    /// assert_eq!(setpoints.angles.pitch, 0.0);
+    /// assert_eq!(setpoints.angles.yaw, 90.0);
+    ///
+    /// assert_eq!(FlightMode::Landed.setpoints(pilot(0.6), DEFAULT_DESCENT_THROTTLE).throttle, 0.0);
+    /// ```
+    pub fn setpoints(&self, pilot: Setpoints, descent_throttle: f32) -> Setpoints {
+        let level = Angles {
+            pitch: 0.0,
+            roll: 0.0,
+            yaw: pilot.angles.yaw,
+        };
+
+        match self {
+            FlightMode::Stabilized => pilot,
+            FlightMode::Descent => Setpoints {
+                throttle: if pilot.throttle < descent_throttle { pilot.throttle } else { descent_throttle },
+                angles: level,
+            },
+            FlightMode::Landed => Setpoints {
+                throttle: 0.0,
+                angles: level,
+            },
+        }
+    }
+}
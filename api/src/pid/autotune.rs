@@ -0,0 +1,276 @@
+//! Relay-feedback (Åström–Hägglund) autotuning.
+
+use math::{
+    sqrt,
+    PI,
+};
+
+use super::Gains;
+
+
+/// Default number of oscillation cycles averaged by the autotuner.
+const DEFAULT_CYCLES: u8 = 4;
+
+/// Number of oscillation cycles discarded while the oscillation settles.
+const SETTLING_CYCLES: u8 = 1;
+
+/// Default time (in microseconds) after which an experiment that has not completed is aborted.
+const DEFAULT_TIMEOUT: u32 = 30_000_000;
+
+
+/// Enumerates the rules used to turn the ultimate gain and period into PID gains.
+///
+/// # Options
+/// `ZieglerNichols`: Classic Ziegler–Nichols rule; fast, but with about 25% overshoot.
+/// `TyreusLuyben`: Tyreus–Luyben rule; slower, but with much less overshoot.
+#[derive(Copy, Clone, PartialEq)]
+pub enum TuningRule {
+    ZieglerNichols,
+    TyreusLuyben,
+}
+
+
+/// Enumerates the states of a relay experiment.
+///
+/// # Options
+/// `Running`: The relay is driving the axis and cycles are being measured.
+/// `Complete`: Enough cycles have been measured; the ultimate point is available.
+/// `TimedOut`: The axis did not oscillate within the timeout, so the experiment was aborted.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum AutotuneState {
+    Running,
+    Complete,
+    TimedOut,
+}
+
+
+/// Holds the result of a relay experiment.
+///
+/// # Fields
+/// `gain`: Ultimate gain (the proportional gain at which the loop oscillates steadily)
+/// `period`: Ultimate period (in seconds) of that oscillation
+#[derive(Copy, Clone)]
+pub struct UltimatePoint {
+    pub gain: f32,
+    pub period: f32,
+}
+
+impl UltimatePoint {
+    /// Returns the PID gains (per second) proposed by the given tuning rule.
+    pub fn gains(&self, rule: TuningRule) -> Gains {
+        let (kp, ti, td) = match rule {
+            TuningRule::ZieglerNichols => (0.6*self.gain, self.period/2.0, self.period/8.0),
+            TuningRule::TyreusLuyben => (self.gain/2.2, 2.2*self.period, self.period/6.3),
+        };
+
+        Gains {
+            kp: kp,
+            ki: kp/ti,
+            kd: kp*td,
+        }
+    }
+}
+
+
+/// Defines a relay-feedback autotuner.
+///
+/// In place of the PID controller, the autotuner drives the axis with a relay: the output is `+amplitude`
+/// while the actual value is below the expected value and `-amplitude` while it is above.
+/// Most plants then settle into a steady oscillation at their ultimate period.
+/// The ultimate gain follows from the relay amplitude `d` and the oscillation amplitude `a` as `4d/(πa)`.
+///
+/// # Examples
+/// ```
+/// use api::pid::{AutotuneState, RelayAutotuner, TuningRule};
+///
+/// // Three first-order lags of one second each have an ultimate gain of 8 and an ultimate period of 2π/√3 seconds
+/// let mut tuner = RelayAutotuner::new(0.0, 1.0, 1000);
+/// // The plant starts below the expected value, which starts the oscillation at close to its final amplitude
+/// let mut lags = [-0.5f32; 3];
+/// while tuner.state() == AutotuneState::Running {
+///     let mut input = tuner.step(lags[2]);
+///     for lag in lags.iter_mut() {
+///         *lag += (input - *lag)*0.001;
+///         input = *lag;
+///     }
+/// }
+///
+/// let ultimate = tuner.ultimate().unwrap();
+/// assert!((ultimate.gain - 8.0).abs() < 1.0);
+/// assert!((ultimate.period - 3.628).abs() < 0.2);
+///
+/// let gains = ultimate.gains(TuningRule::ZieglerNichols);
+/// assert!((gains.kp - 0.6*ultimate.gain).abs() < 1e-4);
+/// ```
+///
+/// A plant that never crosses the expected value (here, one that is stuck) is abandoned after the timeout:
+/// ```
+/// use api::pid::{AutotuneState, RelayAutotuner};
+///
+/// let mut tuner = RelayAutotuner::new(0.0, 1.0, 1000);
+/// tuner.set_timeout(5_000_000);
+/// let mut output = 1.0;
+/// for _ in 0..5000 {
+///     output = tuner.step(-1.0);
+/// }
+///
+/// assert_eq!(tuner.state(), AutotuneState::TimedOut);
+/// assert_eq!(output, 0.0);
+/// assert!(tuner.ultimate().is_none());
+/// ```
+pub struct RelayAutotuner {
+    expected: f32,
+    amplitude: f32,
+    hysteresis: f32,
+    timestep: u16,
+    cycles: u8,
+    timeout: u32,
+    timed_out: bool,
+    output: f32,
+    elapsed: u32,
+    last_switch: Option<u32>,
+    max: f32,
+    min: f32,
+    completed: u8,
+    period_sum: f32,
+    amplitude_sum: f32,
+}
+
+impl RelayAutotuner {
+    /// Constructs a new autotuner that oscillates the axis about `expected` with a relay output of `±amplitude`.
+    /// `timestep` is the nominal interval (in microseconds) between calls to `step`.
+    pub fn new(expected: f32, amplitude: f32, timestep: u16) -> Self {
+        RelayAutotuner {
+            expected: expected,
+            amplitude: amplitude,
+            hysteresis: 0.0,
+            timestep: timestep,
+            cycles: DEFAULT_CYCLES,
+            timeout: DEFAULT_TIMEOUT,
+            timed_out: false,
+            output: amplitude,
+            elapsed: 0,
+            last_switch: None,
+            max: f32::NEG_INFINITY,
+            min: f32::INFINITY,
+            completed: 0,
+            period_sum: 0.0,
+            amplitude_sum: 0.0,
+        }
+    }
+
+    /// Sets the hysteresis band about the expected value, which keeps sensor noise from switching the relay.
+    pub fn set_hysteresis(&mut self, hysteresis: f32) {
+        self.hysteresis = hysteresis;
+    }
+
+    /// Sets the number of oscillation cycles that are averaged.
+    pub fn set_cycles(&mut self, cycles: u8) {
+        self.cycles = cycles;
+    }
+
+    /// Sets the time (in microseconds) after which an experiment that has not completed is aborted.
+    pub fn set_timeout(&mut self, timeout: u32) {
+        self.timeout = timeout;
+    }
+
+    /// Restarts the experiment.
+    pub fn reset(&mut self) {
+        self.timed_out = false;
+        self.output = self.amplitude;
+        self.elapsed = 0;
+        self.last_switch = None;
+        self.max = f32::NEG_INFINITY;
+        self.min = f32::INFINITY;
+        self.completed = 0;
+        self.period_sum = 0.0;
+        self.amplitude_sum = 0.0;
+    }
+
+    /// Updates the experiment over one nominal timestep using the current (actual) value and returns the relay output.
+    pub fn step(&mut self, actual: f32) -> f32 {
+        self.step_dt(actual, self.timestep as u32)
+    }
+
+    /// Updates the experiment using the current (actual) value and the interval `dt` (in microseconds)
+    /// since the previous call, and returns the relay output.
+    /// Once the experiment is complete or has timed out, the output is zero.
+    pub fn step_dt(&mut self, actual: f32, dt: u32) -> f32 {
+        if self.state() != AutotuneState::Running {
+            return 0.0;
+        }
+
+        self.elapsed = self.elapsed.saturating_add(dt);
+        if self.elapsed >= self.timeout {
+            self.timed_out = true;
+            return 0.0;
+        }
+        if actual > self.max {
+            self.max = actual;
+        }
+        if actual < self.min {
+            self.min = actual;
+        }
+
+        let error: f32 = self.expected - actual;
+        if self.output < 0.0 && error > self.hysteresis {
+            // Each switch from negative to positive output completes one cycle
+            self.output = self.amplitude;
+            if let Some(last_switch) = self.last_switch {
+                self.record_cycle(self.elapsed - last_switch);
+            }
+            self.last_switch = Some(self.elapsed);
+            self.max = actual;
+            self.min = actual;
+        } else if self.output > 0.0 && error < -self.hysteresis {
+            self.output = -self.amplitude;
+        }
+
+        if self.is_complete() { 0.0 } else { self.output }
+    }
+
+    /// Records one completed oscillation cycle lasting `period` microseconds.
+    fn record_cycle(&mut self, period: u32) {
+        self.completed += 1;
+        if self.completed > SETTLING_CYCLES {
+            self.period_sum += (period as f32)*1e-6;
+            self.amplitude_sum += (self.max - self.min)/2.0;
+        }
+    }
+
+    /// Returns true once enough oscillation cycles have been measured.
+    pub fn is_complete(&self) -> bool {
+        self.completed >= self.cycles.saturating_add(SETTLING_CYCLES)
+    }
+
+    /// Returns the state of the experiment.
+    pub fn state(&self) -> AutotuneState {
+        if self.timed_out {
+            AutotuneState::TimedOut
+        } else if self.is_complete() {
+            AutotuneState::Complete
+        } else {
+            AutotuneState::Running
+        }
+    }
+
+    /// Returns the measured ultimate gain and period, or `None` if the experiment is not complete.
+    pub fn ultimate(&self) -> Option<UltimatePoint> {
+        if !self.is_complete() || self.cycles == 0 {
+            return None;
+        }
+
+        let cycles = self.cycles as f32;
+        let oscillation: f32 = self.amplitude_sum/cycles;
+        // The hysteresis delays each switch, which is corrected for by the describing function of a relay with hysteresis
+        let effective: f32 = sqrt(oscillation*oscillation - self.hysteresis*self.hysteresis);
+        if effective <= 0.0 {
+            return None;
+        }
+
+        Some(UltimatePoint {
+            gain: 4.0*self.amplitude/(PI*effective),
+            period: self.period_sum/cycles,
+        })
+    }
+}
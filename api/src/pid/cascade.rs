@@ -0,0 +1,194 @@
+//! Cascaded angle and rate control for stabilized flight.
+
+use crate::imu::{
+    Angles,
+    Gyro,
+};
+use super::ControlVariable;
+
+
+/// Minutes of arc in one full turn, used for wrapping yaw errors.
+const ARCMIN_PER_TURN: f32 = 360.0*60.0;
+
+
+/// Defines a cascaded controller for one axis.
+///
+/// The outer (angle) loop compares the expected angle with the measured angle (in minutes of arc)
+/// and produces a rate setpoint (in degrees per second).
+/// The inner (rate) loop compares that setpoint with the gyroscope rate and produces the correction.
+/// The rate loop reacts to disturbances long before they show up as an angle error,
+/// so it can run with much higher gains than a single angle loop.
+///
+/// Gains, output limits, and filters for each stage are set through `angle_loop` and `rate_loop`.
+/// The output limits of the angle loop are the maximum commanded rate.
+///
+/// # Examples
+/// ```
+/// use api::pid::CascadeController;
+///
+/// // Level a simulated axis (an inertia driven directly by the correction) that starts 10 degrees off
+/// let mut control = CascadeController::new(1000);
+/// control.angle_loop().set_gains(0.1, 0.0, 0.0);
+/// control.angle_loop().set_output_limits(-200.0, 200.0);
+/// control.rate_loop().set_gains(20.0, 0.0, 0.0);
+///
+/// let (mut angle, mut rate) = (600.0f32, 0.0f32);
+/// for _ in 0..3000 {
+///     control.step(angle, rate);
+///     rate += control.get_correction()*0.001;
+///     angle += rate*60.0*0.001;
+/// }
+/// assert!(angle.abs() < 6.0 && rate.abs() < 1.0);
+/// ```
+pub struct CascadeController {
+    angle: ControlVariable,
+    rate: ControlVariable,
+}
+
+impl CascadeController {
+    /// Constructs a new cascaded controller that holds an angle of zero.
+    /// `timestep` is the nominal interval (in microseconds) between calls to `step`.
+    pub fn new(timestep: u16) -> Self {
+        CascadeController {
+            angle: ControlVariable::new(0.0, timestep),
+            rate: ControlVariable::new(0.0, timestep),
+        }
+    }
+
+    /// Returns the outer (angle) loop, for setting its gains and limits.
+    pub fn angle_loop(&mut self) -> &mut ControlVariable {
+        &mut self.angle
+    }
+
+    /// Returns the inner (rate) loop, for setting its gains and limits.
+    pub fn rate_loop(&mut self) -> &mut ControlVariable {
+        &mut self.rate
+    }
+
+    /// Updates the expected angle (in minutes of arc).
+    pub fn set_expected(&mut self, angle: f32) {
+        self.angle.set_expected(angle);
+    }
+
+    /// Clears the state of both loops.
+    pub fn reset(&mut self) {
+        self.angle.reset();
+        self.rate.reset();
+    }
+
+    /// Updates both loops over one nominal timestep using the measured angle (in minutes of arc) and rate (in degrees per second).
+    pub fn step(&mut self, angle: f32, rate: f32) {
+        self.angle.step(angle);
+        self.rate.set_expected(self.angle.get_correction());
+        self.rate.step(rate);
+    }
+
+    /// Updates both loops using the measured angle (in minutes of arc), rate (in degrees per second),
+    /// and the interval `dt` (in microseconds) since the previous call.
+    pub fn step_dt(&mut self, angle: f32, rate: f32, dt: u32) {
+        self.angle.step_dt(angle, dt);
+        self.rate.set_expected(self.angle.get_correction());
+        self.rate.step_dt(rate, dt);
+    }
+
+    /// Returns the rate setpoint (in degrees per second) produced by the angle loop.
+    pub fn get_rate_setpoint(&mut self) -> f32 {
+        self.angle.get_correction()
+    }
+
+    /// Returns the correction produced by the rate loop.
+    pub fn get_correction(&mut self) -> f32 {
+        self.rate.get_correction()
+    }
+}
+
+
+/// Holds one correction per axis, as produced by `AttitudeController`.
+///
+/// # Fields
+/// `roll`: Roll correction
+/// `pitch`: Pitch correction
+/// `yaw`: Yaw correction
+#[derive(Copy, Clone)]
+pub struct Corrections {
+    pub roll: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+}
+
+
+/// Defines a cascaded attitude controller with independent roll, pitch, and yaw axes.
+///
+/// Angles follow the convention of `ahrs::to_angles`: roll is the rotation about the sensor Y axis,
+/// pitch is the rotation about the negative sensor X axis, and yaw is the rotation about the sensor Z axis.
+/// The gyroscope rates are mapped onto the same axes.
+/// Yaw errors are wrapped, so the yaw loop always turns the short way round.
+///
+/// # Fields
+/// `roll`: Roll axis controller
+/// `pitch`: Pitch axis controller
+/// `yaw`: Yaw axis controller
+pub struct AttitudeController {
+    pub roll: CascadeController,
+    pub pitch: CascadeController,
+    pub yaw: CascadeController,
+}
+
+impl AttitudeController {
+    /// Constructs a new attitude controller that holds level with a heading of zero.
+    /// `timestep` is the nominal interval (in microseconds) between calls to `step`.
+    pub fn new(timestep: u16) -> Self {
+        AttitudeController {
+            roll: CascadeController::new(timestep),
+            pitch: CascadeController::new(timestep),
+            yaw: CascadeController::new(timestep),
+        }
+    }
+
+    /// Updates the expected angles (in minutes of arc).
+    pub fn set_expected(&mut self, angles: &Angles) {
+        self.roll.set_expected(angles.roll);
+        self.pitch.set_expected(angles.pitch);
+        self.yaw.set_expected(angles.yaw);
+    }
+
+    /// Clears the state of every axis.
+    pub fn reset(&mut self) {
+        self.roll.reset();
+        self.pitch.reset();
+        self.yaw.reset();
+    }
+
+    /// Updates every axis using the estimated angles, the gyroscope rates,
+    /// and the interval `dt` (in microseconds) since the previous call.
+    pub fn step_dt(&mut self, angles: &Angles, gyro: &Gyro, dt: u32) {
+        self.roll.step_dt(angles.roll, gyro.y, dt);
+        self.pitch.step_dt(angles.pitch, -gyro.x, dt);
+
+        // Measure the yaw angle relative to the expected heading, so that the error never exceeds half a turn
+        let expected: f32 = self.yaw.angle.expected();
+        let yaw: f32 = expected - wrap(expected - angles.yaw);
+        self.yaw.step_dt(yaw, gyro.z, dt);
+    }
+
+    /// Returns the corrections for every axis.
+    pub fn get_corrections(&mut self) -> Corrections {
+        Corrections {
+            roll: self.roll.get_correction(),
+            pitch: self.pitch.get_correction(),
+            yaw: self.yaw.get_correction(),
+        }
+    }
+}
+
+
+/// Wraps an angle (in minutes of arc) into the range of half a turn either way.
+fn wrap(angle: f32) -> f32 {
+    let mut angle = angle % ARCMIN_PER_TURN;
+    if angle > ARCMIN_PER_TURN/2.0 {
+        angle -= ARCMIN_PER_TURN;
+    } else if angle < -ARCMIN_PER_TURN/2.0 {
+        angle += ARCMIN_PER_TURN;
+    }
+    angle
+}
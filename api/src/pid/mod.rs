@@ -1,6 +1,140 @@
 //! PID Control Library
+//!
+//! Every controller has a `step` method, which assumes that exactly one nominal timestep has passed since the previous call,
+//! and a `step_dt` method, which takes the measured interval (in microseconds) instead.
+
+use math::filter::{
+    Filter,
+    Pt1,
+};
+use math::Real;
+
+mod cascade;
+pub use cascade::{
+    CascadeController,
+    AttitudeController,
+    Corrections,
+};
+mod autotune;
+pub use autotune::{
+    AutotuneState,
+    RelayAutotuner,
+    TuningRule,
+    UltimatePoint,
+};
+mod schedule;
+pub use schedule::{
+    Breakpoint,
+    GainSchedule,
+    Tpa,
+};
+
+/// Enumerates the strategies used to prevent integral windup while the output is saturated.
+///
+/// # Options
+/// `None`: The integral term accumulates regardless of saturation (limited only by the integral limit).
+/// `Clamping`: Conditional integration; the error is not integrated while the output is saturated in the direction of the error.
+/// `BackCalculation(gain)`: The integral term is driven back towards the output limit at a rate (per second) of `gain`
+/// times the amount by which the output exceeded the limit.
+#[derive(Copy, Clone, PartialEq)]
+pub enum AntiWindup {
+    None,
+    Clamping,
+    BackCalculation(f32),
+}
+
+/// Enumerates the signals that the derivative term can be computed from.
+///
+/// # Options
+/// `Error`: The derivative of the error.  Every change of the expected value produces a spike (derivative kick).
+/// `Measurement`: The negative derivative of the actual value.  This responds identically to disturbances,
+/// but changes of the expected value do not produce a derivative kick.
+#[derive(Copy, Clone, PartialEq)]
+pub enum DerivativeMode {
+    Error,
+    Measurement,
+}
+
+/// Holds a set of PID gains (per second).
+///
+/// # Fields
+/// `kp`: Proportional gain
+/// `ki`: Integral gain
+/// `kd`: Derivative gain
+#[derive(Copy, Clone)]
+pub struct Gains {
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+}
 
 /// Defines an abstract control variable that can be controlled by PID control
+///
+/// Gains are expressed per second: `ki` multiplies the integral of the error (in error-seconds),
+/// `kd` multiplies the derivative (in error per second),
+/// and `kf` multiplies the rate of change of the expected value (in units per second).
+///
+/// The proportional term acts on `b*expected - actual`, where `b` is the setpoint weight.
+/// Lowering `b` softens the response to setpoint changes without changing the response to disturbances,
+/// while the feed-forward term passes setpoint changes straight through to the correction.
+///
+/// # Examples
+/// ```
+/// use api::pid::{ControlVariable, DerivativeMode};
+///
+/// // Returns the derivative correction just after the expected value steps from 0 to 100
+/// fn kick(mode: DerivativeMode) -> f32 {
+///     let mut control = ControlVariable::new(0.0, 1000);
+///     control.set_gains(0.0, 0.0, 1.0);
+///     control.set_derivative_mode(mode);
+///     control.step(0.0);
+///     control.step(0.0);
+///
+///     control.set_expected(100.0);
+///     control.step(0.0);
+///     control.get_correction()
+/// }
+///
+/// assert!(kick(DerivativeMode::Error) > 0.0);
+/// assert_eq!(kick(DerivativeMode::Measurement), 0.0);
+/// ```
+///
+/// Over a whole step response, with the plant integrating the correction and the derivative acting on the measurement
+/// through a 10 Hz filter, the correction never exceeds the proportional term, so the setpoint step never kicks the derivative:
+/// ```
+/// use api::pid::{ControlVariable, DerivativeMode};
+///
+/// let mut control = ControlVariable::new(0.0, 1000);
+/// control.set_gains(2.0, 0.0, 0.05);
+/// control.set_derivative_mode(DerivativeMode::Measurement);
+/// control.set_derivative_cutoff(10.0);
+/// let mut actual = 0.0f32;
+/// control.step(actual);
+///
+/// control.set_expected(100.0);
+/// for _ in 0..3000 {
+///     control.step(actual);
+///     let correction = control.get_correction();
+///     assert!(correction <= 2.0*(100.0 - actual) + 1e-3);
+///     actual += correction*0.001;
+/// }
+/// assert!((actual - 100.0).abs() < 0.5);
+///
+/// // The filtered derivative of a steady ramp (1000 units per second) rises like a first-order filter at the cutoff:
+/// // to 63% after one time constant (1/(2π·10 Hz), or about 16 ms) and fully settled after ten
+/// let mut control = ControlVariable::new(0.0, 1000);
+/// control.set_gains(0.0, 0.0, 1.0);
+/// control.set_derivative_mode(DerivativeMode::Measurement);
+/// control.set_derivative_cutoff(10.0);
+/// control.step(0.0);
+/// let mut derivative = Vec::new();
+/// for i in 1..=160 {
+///     control.step(i as f32);
+///     derivative.push(-control.get_correction());
+/// }
+/// assert!((derivative[15]/1000.0 - 0.63).abs() < 0.03);
+/// assert!((derivative[159]/1000.0 - 1.0).abs() < 0.01);
+/// ```
 pub struct ControlVariable {
     expected: f32,
     actual: f32,
@@ -9,17 +143,36 @@ pub struct ControlVariable {
     proportion: f32,
     integral: f32,
     derivative: f32,
+    feed_forward: f32,
     kp: f32,
     ki: f32,
     kd: f32,
+    kf: f32,
+    setpoint_weight: f32,
+    output_min: f32,
+    output_max: f32,
+    integral_limit: f32,
+    anti_windup: AntiWindup,
+    correction: f32,
+    derivative_mode: DerivativeMode,
+    derivative_filter: Option<Pt1>,
+    derivative_cutoff: f32,
+    filter_dt: u32,
+    previous_actual: f32,
+    previous_expected: f32,
+    initialized: bool,
 }
 
+// These defaults match the previous per-microsecond gains (ki = 0.000_001, kd = 0.000_1),
+// converted to per-second gains as ki × 1e6 and kd ÷ 1e6
 const DEFAULT_KP: f32 = 0.1;
-const DEFAULT_KI: f32 = 0.000_001;
-const DEFAULT_KD: f32 = 0.000_1;
+const DEFAULT_KI: f32 = 1.0;
+const DEFAULT_KD: f32 = 0.000_000_000_1;
 
 
 impl ControlVariable {
+    /// Constructs a new control variable.
+    /// `timestep` is the nominal interval (in microseconds) between calls to `step`.
     pub fn new(expected: f32, timestep: u16) -> Self {
         ControlVariable {
             expected: expected,
@@ -29,9 +182,24 @@ impl ControlVariable {
             proportion: 0.0f32,
             integral: 0.0f32,
             derivative: 0.0f32,
+            feed_forward: 0.0f32,
             kp: DEFAULT_KP,
             ki: DEFAULT_KI,
             kd: DEFAULT_KD,
+            kf: 0.0f32,
+            setpoint_weight: 1.0f32,
+            output_min: f32::NEG_INFINITY,
+            output_max: f32::INFINITY,
+            integral_limit: f32::INFINITY,
+            anti_windup: AntiWindup::Clamping,
+            correction: 0.0f32,
+            derivative_mode: DerivativeMode::Error,
+            derivative_filter: None,
+            derivative_cutoff: 0.0f32,
+            filter_dt: timestep as u32,
+            previous_actual: 0.0f32,
+            previous_expected: 0.0f32,
+            initialized: false,
         }
     }
 
@@ -40,14 +208,46 @@ impl ControlVariable {
         self.expected = expected;
     }
 
+    /// Returns the expected value of the control variable.
+    pub fn expected(&self) -> f32 {
+        self.expected
+    }
+
     /// Sets the proportional gain.
     pub fn set_kp(&mut self, gain: f32) {
         self.kp = gain;
     }
 
     /// Sets the integral gain.
+    /// The error is not integrated while the integral gain is zero, and the integral is clamped to the integral limit for the new gain,
+    /// so changing the gain never makes the integral contribution jump past the limit.
+    ///
+    /// # Examples
+    /// ```
+    /// use api::pid::ControlVariable;
+    ///
+    /// // Nothing accumulates while the integral gain is zero
+    /// let mut control = ControlVariable::new(1.0, 1000);
+    /// control.set_gains(0.0, 0.0, 0.0);
+    /// control.set_integral_limit(0.5);
+    /// for _ in 0..10_000 {
+    ///     control.step(0.0);
+    /// }
+    /// control.set_ki(1.0);
+    /// control.step(0.0);
+    /// assert!((control.get_correction() - 0.001).abs() < 1e-6);
+    ///
+    /// // Raising the gain keeps the integral contribution within the limit
+    /// for _ in 0..10_000 {
+    ///     control.step(0.0);
+    /// }
+    /// control.set_ki(10.0);
+    /// control.step(1.0);
+    /// assert!((control.get_correction() - 0.5).abs() < 1e-6);
+    /// ```
     pub fn set_ki(&mut self, gain: f32) {
         self.ki = gain;
+        self.clamp_integral();
     }
 
     /// Sets the derivative gain.
@@ -55,6 +255,37 @@ impl ControlVariable {
         self.kd = gain;
     }
 
+    /// Sets the feed-forward gain, which multiplies the rate of change of the expected value.
+    pub fn set_kf(&mut self, gain: f32) {
+        self.kf = gain;
+    }
+
+    /// Sets the proportional setpoint weight `b` (normally between 0 and 1).
+    /// The proportional term acts on `b*expected - actual`; the integral and derivative terms are unaffected.
+    ///
+    /// # Examples
+    /// ```
+    /// use api::pid::ControlVariable;
+    ///
+    /// // With a setpoint weight of zero, a step of the expected value moves the output only through feed-forward
+    /// let mut control = ControlVariable::new(0.0, 1000);
+    /// control.set_gains(1.0, 0.0, 0.0);
+    /// control.set_setpoint_weight(0.0);
+    /// control.set_kf(0.01);
+    /// control.step(0.0);
+    ///
+    /// control.set_expected(10.0);
+    /// control.step(0.0);
+    /// assert!((control.get_correction() - 100.0).abs() < 1e-3);
+    ///
+    /// // Once the expected value stops changing, the feed-forward term vanishes
+    /// control.step(0.0);
+    /// assert_eq!(control.get_correction(), 0.0);
+    /// ```
+    pub fn set_setpoint_weight(&mut self, weight: f32) {
+        self.setpoint_weight = weight;
+    }
+
     /// Sets all gains.
     pub fn set_gains(&mut self, kp: f32, ki: f32, kd: f32) {
         self.set_kp(kp);
@@ -62,14 +293,106 @@ impl ControlVariable {
         self.set_kd(kd);
     }
 
+    /// Sets all gains from a set of gains, such as one produced by a `GainSchedule` or `Tpa`.
+    /// The integral is rescaled so that the integral contribution to the correction does not jump.
+    pub fn set_gains_from(&mut self, gains: Gains) {
+        if gains.ki != 0.0 {
+            self.integral *= self.ki/gains.ki;
+        }
+        self.set_gains(gains.kp, gains.ki, gains.kd);
+    }
+
+    /// Returns the proportional, integral, and derivative gains.
+    pub fn gains(&self) -> Gains {
+        Gains {
+            kp: self.kp,
+            ki: self.ki,
+            kd: self.kd,
+        }
+    }
+
+    /// Limits the PID correction to lie between `min` and `max`.
+    pub fn set_output_limits(&mut self, min: f32, max: f32) {
+        self.output_min = min;
+        self.output_max = max;
+    }
+
+    /// Limits the magnitude of the integral contribution (`ki` times the integral) to the correction.
+    pub fn set_integral_limit(&mut self, limit: f32) {
+        self.integral_limit = limit;
+    }
+
+    /// Sets the anti-windup strategy used while the correction is saturated at an output limit.
+    pub fn set_anti_windup(&mut self, anti_windup: AntiWindup) {
+        self.anti_windup = anti_windup;
+    }
+
+    /// Sets the signal that the derivative term is computed from.
+    pub fn set_derivative_mode(&mut self, mode: DerivativeMode) {
+        self.derivative_mode = mode;
+    }
+
+    /// Low-pass filters the derivative term with a first-order filter of the given cutoff frequency (in Hz).
+    /// A cutoff of zero (or less) removes the filter.
+    pub fn set_derivative_cutoff(&mut self, cutoff: f32) {
+        self.derivative_cutoff = cutoff;
+        self.filter_dt = self.timestep as u32;
+        self.derivative_filter = if cutoff > 0.0 {
+            Some(Pt1::new(cutoff, 1_000_000.0/(self.timestep as f32)))
+        } else {
+            None
+        };
+    }
+
+    /// Clears the integral and derivative state (for example, when the motors are armed or disarmed).
+    pub fn reset(&mut self) {
+        self.error = 0.0;
+        self.proportion = 0.0;
+        self.integral = 0.0;
+        self.derivative = 0.0;
+        self.feed_forward = 0.0;
+        self.correction = 0.0;
+        self.initialized = false;
+        if let Some(filter) = self.derivative_filter.as_mut() {
+            filter.reset();
+        }
+    }
+
     /// Updates the error term.
     fn update_error(&mut self) {
         self.error = self.expected - self.actual;
     }
 
-    /// Updates the PID control variable using the current (actual) value of the variable.
+    /// Updates the PID control variable over one nominal timestep, using the current (actual) value of the variable.
     pub fn step(&mut self, actual: f32) {
+        self.step_dt(actual, self.timestep as u32);
+    }
+
+    /// Updates the PID control variable using the current (actual) value of the variable
+    /// and the measured interval `dt` (in microseconds) since the previous call.
+    /// An interval of zero updates only the proportional term.
+    ///
+    /// # Examples
+    /// ```
+    /// use api::pid::ControlVariable;
+    ///
+    /// // One 2 ms step integrates as much error as two 1 ms steps
+    /// let mut nominal = ControlVariable::new(1.0, 1000);
+    /// let mut measured = ControlVariable::new(1.0, 1000);
+    /// for control in [&mut nominal, &mut measured].iter_mut() {
+    ///     control.set_gains(0.0, 1.0, 0.0);
+    /// }
+    /// nominal.step(0.0);
+    /// nominal.step(0.0);
+    /// measured.step_dt(0.0, 2000);
+    /// assert!((nominal.get_correction() - 0.002).abs() < 1e-6);
+    /// assert!((measured.get_correction() - 0.002).abs() < 1e-6);
+    /// ```
+    pub fn step_dt(&mut self, actual: f32, dt: u32) {
+        let dt_seconds: f32 = (dt as f32)*1e-6;
         let previous = self.error;
+        let previous_actual = self.previous_actual;
+        let previous_expected = self.previous_expected;
 
         // Set the actual value of the control variable
         self.actual = actual;
@@ -77,19 +400,84 @@ impl ControlVariable {
         // Update the error term
         self.update_error();
 
-        // Set the proportional term
-        self.proportion = self.error as f32;
+        // Set the proportional term, weighting the expected value
+        self.proportion = self.setpoint_weight*self.expected - self.actual;
+
+        if dt == 0 {
+            self.correction = Real::clamp(self.sum_terms(), self.output_min, self.output_max);
+            return;
+        }
+
+        // Set the derivative and feed-forward terms
+        // There is no previous sample on the first step, so both are taken to be zero
+        let derivative: f32 = if !self.initialized {
+            0.0
+        } else {
+            match self.derivative_mode {
+                DerivativeMode::Error => (self.error - previous)/dt_seconds,
+                DerivativeMode::Measurement => (previous_actual - actual)/dt_seconds,
+            }
+        };
+        self.feed_forward = if !self.initialized {
+            0.0
+        } else {
+            (self.expected - previous_expected)/dt_seconds
+        };
+        if dt != self.filter_dt && self.derivative_cutoff > 0.0 {
+            // Keep the filter cutoff fixed in Hz when the interval varies
+            if let Some(filter) = self.derivative_filter.as_mut() {
+                filter.set_cutoff(self.derivative_cutoff, 1.0/dt_seconds);
+            }
+            self.filter_dt = dt;
+        }
+        self.derivative = match self.derivative_filter.as_mut() {
+            Some(filter) => filter.apply(derivative),
+            None => derivative,
+        };
+        self.previous_actual = actual;
+        self.previous_expected = self.expected;
+        self.initialized = true;
+
+        // Only integrate the error if the integral term is in use,
+        // and if doing so would not drive a saturated output further into saturation
+        let unsaturated: f32 = self.sum_terms();
+        let saturated_high: bool = unsaturated > self.output_max && self.error > 0.0;
+        let saturated_low: bool = unsaturated < self.output_min && self.error < 0.0;
+        if self.ki != 0.0 && (self.anti_windup != AntiWindup::Clamping || !(saturated_high || saturated_low)) {
+            // Compute the integral of the error term
+            self.integral += self.error * dt_seconds;
+        }
+
+        // Bleed off the integral contribution by the amount that the output exceeds its limits
+        if let AntiWindup::BackCalculation(gain) = self.anti_windup {
+            if self.ki != 0.0 {
+                let unsaturated: f32 = self.sum_terms();
+                let excess: f32 = Real::clamp(unsaturated, self.output_min, self.output_max) - unsaturated;
+                self.integral += gain * excess * dt_seconds / self.ki;
+            }
+        }
 
-        // Compute the integral of the error term
-        self.integral += (self.error as f32) * (self.timestep as f32);
+        self.clamp_integral();
 
-        // Set the derivative of the error term
-        self.derivative = ((self.error - previous) as f32)/(self.timestep as f32);
+        self.correction = Real::clamp(self.sum_terms(), self.output_min, self.output_max);
     }
 
     /// Returns the PID correction term (the amount by which to adjust the input to correct the output).
+    /// The correction is limited to the range set by `set_output_limits`.
     pub fn get_correction(&mut self) -> f32 {
-        let correction: f32 = self.kp*self.proportion + self.ki*self.integral + self.kd*self.derivative;
-        correction
+        self.correction
     }
-}
\ No newline at end of file
+
+    /// Returns the unsaturated sum of the proportional, integral, derivative, and feed-forward contributions.
+    fn sum_terms(&self) -> f32 {
+        self.kp*self.proportion + self.ki*self.integral + self.kd*self.derivative + self.kf*self.feed_forward
+    }
+
+    /// Limits the integral so that its contribution to the correction lies within the integral limit.
+    fn clamp_integral(&mut self) {
+        if self.ki != 0.0 {
+            let limit: f32 = self.integral_limit/Real::abs(self.ki);
+            self.integral = Real::clamp(self.integral, -limit, limit);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Gain scheduling and throttle PID attenuation (TPA).
+
+use super::Gains;
+
+
+/// Holds the gains to use at one value of the scheduling variable.
+///
+/// # Fields
+/// `input`: Value of the scheduling variable (such as throttle or battery voltage)
+/// `gains`: Gains to use at that value
+#[derive(Copy, Clone)]
+pub struct Breakpoint {
+    pub input: f32,
+    pub gains: Gains,
+}
+
+
+/// Defines a table of gains indexed by a scheduling variable, such as throttle or battery voltage.
+///
+/// Between breakpoints the gains are interpolated linearly; beyond the first and last breakpoints they are held constant.
+/// The breakpoints must be sorted by increasing input.
+///
+/// # Examples
+/// ```
+/// use api::pid::{Breakpoint, Gains, GainSchedule};
+///
+/// // Soften the gains as the battery voltage rises from 10.5 V to 12.5 V
+/// let schedule = GainSchedule::new([
+///     Breakpoint { input: 10.5, gains: Gains { kp: 1.2, ki: 0.4, kd: 0.02 } },
+///     Breakpoint { input: 12.5, gains: Gains { kp: 0.8, ki: 0.4, kd: 0.01 } },
+/// ]);
+///
+/// assert!((schedule.gains(11.5).kp - 1.0).abs() < 1e-6);
+/// assert!((schedule.gains(11.5).kd - 0.015).abs() < 1e-6);
+/// assert_eq!(schedule.gains(9.0).kp, 1.2);
+/// assert_eq!(schedule.gains(13.0).kp, 0.8);
+/// ```
+pub struct GainSchedule<const N: usize> {
+    breakpoints: [Breakpoint; N],
+}
+
+impl<const N: usize> GainSchedule<N> {
+    /// Constructs a new gain schedule from breakpoints sorted by increasing input.
+    pub fn new(breakpoints: [Breakpoint; N]) -> Self {
+        GainSchedule {
+            breakpoints: breakpoints,
+        }
+    }
+
+    /// Returns the gains at the given value of the scheduling variable.
+    ///
+    /// # Panics
+    /// This function panics if the schedule has no breakpoints.
+    pub fn gains(&self, input: f32) -> Gains {
+        let first = &self.breakpoints[0];
+        if input <= first.input {
+            return first.gains;
+        }
+
+        for pair in self.breakpoints.windows(2) {
+            let (low, high) = (&pair[0], &pair[1]);
+            if input <= high.input {
+                let span: f32 = high.input - low.input;
+                let fraction: f32 = if span > 0.0 { (input - low.input)/span } else { 1.0 };
+                return Gains {
+                    kp: lerp(low.gains.kp, high.gains.kp, fraction),
+                    ki: lerp(low.gains.ki, high.gains.ki, fraction),
+                    kd: lerp(low.gains.kd, high.gains.kd, fraction),
+                };
+            }
+        }
+
+        self.breakpoints[N - 1].gains
+    }
+}
+
+
+/// Defines a throttle PID attenuation (TPA) curve.
+///
+/// Below the breakpoint the gains are unchanged.
+/// Above it, the proportional and derivative gains fall linearly, reaching `1 - rate` times their value at full throttle.
+/// The integral gain is never attenuated.
+///
+/// # Examples
+/// ```
+/// use api::pid::{Gains, Tpa};
+///
+/// // Attenuate P and D by up to 40% above half throttle
+/// let tpa = Tpa::new(0.5, 0.4);
+/// let gains = Gains { kp: 1.0, ki: 0.5, kd: 0.02 };
+///
+/// assert_eq!(tpa.attenuate(gains, 0.3).kp, 1.0);
+/// assert!((tpa.attenuate(gains, 0.75).kp - 0.8).abs() < 1e-6);
+/// assert!((tpa.attenuate(gains, 1.0).kd - 0.012).abs() < 1e-6);
+/// assert_eq!(tpa.attenuate(gains, 1.0).ki, 0.5);
+/// ```
+#[derive(Copy, Clone)]
+pub struct Tpa {
+    breakpoint: f32,
+    rate: f32,
+}
+
+impl Tpa {
+    /// Constructs a new TPA curve that starts at `breakpoint` (a throttle between 0 and 1)
+    /// and attenuates by `rate` (between 0 and 1) at full throttle.
+    pub fn new(breakpoint: f32, rate: f32) -> Self {
+        Tpa {
+            breakpoint: breakpoint,
+            rate: rate,
+        }
+    }
+
+    /// Returns the factor (between `1 - rate` and 1) by which P and D are scaled at the given throttle.
+    pub fn factor(&self, throttle: f32) -> f32 {
+        if throttle <= self.breakpoint || self.breakpoint >= 1.0 {
+            return 1.0;
+        }
+
+        let fraction: f32 = if throttle >= 1.0 {
+            1.0
+        } else {
+            (throttle - self.breakpoint)/(1.0 - self.breakpoint)
+        };
+        1.0 - self.rate*fraction
+    }
+
+    /// Returns the gains with P and D attenuated for the given throttle (between 0 and 1).
+    pub fn attenuate(&self, gains: Gains, throttle: f32) -> Gains {
+        let factor: f32 = self.factor(throttle);
+        Gains {
+            kp: gains.kp*factor,
+            ki: gains.ki,
+            kd: gains.kd*factor,
+        }
+    }
+}
+
+
+/// Interpolates linearly from `a` (at a fraction of 0) to `b` (at a fraction of 1).
+#[inline(always)]
+fn lerp(a: f32, b: f32, fraction: f32) -> f32 {
+    a + (b - a)*fraction
+}
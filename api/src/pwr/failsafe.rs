@@ -0,0 +1,247 @@
+//! Low-battery failsafe.
+
+use math::Real;
+use sys::Status;
+
+use crate::imu::Gyro;
+use crate::mode::FlightMode;
+use super::{
+    PowerLevel,
+    PowerMonitor,
+};
+
+
+/// Default internal resistance of the battery (in ohms).
+const DEFAULT_INTERNAL_RESISTANCE: f32 = 0.03;
+
+/// Default time (in microseconds) that the battery must stay low before the warning is raised.
+const DEFAULT_WARNING_HOLD: u32 = 2_000_000;
+
+/// Default time (in microseconds) that the battery must stay depleted before the descent is commanded.
+const DEFAULT_CRITICAL_HOLD: u32 = 2_000_000;
+
+/// Default pilot throttle (between 0 and 1) at or below which the motors are taken to be idling.
+const DEFAULT_IDLE_THROTTLE: f32 = 0.05;
+
+/// Default largest rotation rate (in degrees per second) about any axis at which the craft is taken to be still.
+const DEFAULT_STILL_RATE: f32 = 5.0;
+
+/// Default time (in microseconds) that the craft must stay idle and still during a descent before touchdown is detected.
+const DEFAULT_LANDING_HOLD: u32 = 2_000_000;
+
+
+/// Defines a low-battery failsafe.
+///
+/// Under load, the battery voltage sags by the current times the internal resistance of the battery.
+/// The failsafe adds this sag back before comparing the voltage per cell against the thresholds of the power monitor,
+/// so punching the throttle does not trip it.
+/// Each threshold must also stay crossed for its hold time.
+///
+/// Once reached, a level is latched (the voltage recovers as soon as the load is removed, so recovery is not trusted).
+/// At the warning level the power status becomes `Warning`; at the critical level it becomes `Critical`
+/// and the failsafe commands a controlled descent.
+/// The descent continues until touchdown is detected (the pilot throttle stays at idle and the gyroscope stays still for the landing hold time)
+/// or confirmed (for example, when the pilot disarms); only then does the failsafe report that the craft has landed.
+///
+/// # Examples
+/// ```
+/// use api::imu::Gyro;
+/// use api::mode::FlightMode;
+/// use api::pwr::{BatteryFailsafe, PowerLevel, PowerMonitor};
+///
+/// // Feeds a recorded trace of (voltage, current) samples, taken every 100 ms, through the failsafe
+/// fn replay(trace: &[(f32, f32)]) -> BatteryFailsafe {
+///     let mut monitor = PowerMonitor::new(11.0);
+///     let mut failsafe = BatteryFailsafe::new();
+///     failsafe.set_internal_resistance(0.05);
+///     for &(voltage, current) in trace {
+///         for _ in 0..10 {
+///             monitor.update(voltage, Some(current), 10_000);
+///             failsafe.update(&monitor, 10_000);
+///         }
+///     }
+///     failsafe
+/// }
+///
+/// // A three second punch to 30 A sags a healthy 3S pack to 10.3 V, but the failsafe holds
+/// let punch = [(12.3, 5.0); 10].iter()
+///     .chain([(10.3, 30.0); 30].iter())
+///     .chain([(12.2, 5.0); 10].iter())
+///     .cloned().collect::<Vec<_>>();
+/// let failsafe = replay(&punch);
+/// assert_eq!(failsafe.level(), PowerLevel::Ok);
+/// assert_eq!(failsafe.flight_mode(), FlightMode::Stabilized);
+///
+/// // A pack that runs down while hovering at 10 A warns first and then descends
+/// let discharge = (0..400).map(|i| (12.4 - 0.01*(i as f32), 10.0)).collect::<Vec<_>>();
+/// let failsafe = replay(&discharge[..280]);
+/// assert_eq!(failsafe.level(), PowerLevel::Warning);
+/// assert_eq!(failsafe.flight_mode(), FlightMode::Stabilized);
+/// let mut failsafe = replay(&discharge);
+/// assert_eq!(failsafe.level(), PowerLevel::Critical);
+/// assert_eq!(failsafe.flight_mode(), FlightMode::Descent);
+///
+/// // The descent lasts as long as the craft is flying, however long that takes
+/// let flying = Gyro { x: 20.0, y: -10.0, z: 0.0 };
+/// for _ in 0..10_000 {
+///     failsafe.update_touchdown(0.3, &flying, 10_000);
+/// }
+/// assert_eq!(failsafe.flight_mode(), FlightMode::Descent);
+///
+/// // Touchdown is detected once the throttle is at idle and the craft is still for two seconds
+/// let still = Gyro { x: 0.5, y: -0.5, z: 0.0 };
+/// for _ in 0..199 {
+///     failsafe.update_touchdown(0.0, &still, 10_000);
+/// }
+/// assert_eq!(failsafe.flight_mode(), FlightMode::Descent);
+/// failsafe.update_touchdown(0.0, &still, 10_000);
+/// assert_eq!(failsafe.flight_mode(), FlightMode::Landed);
+/// ```
+pub struct BatteryFailsafe {
+    internal_resistance: f32,
+    warning_hold: u32,
+    critical_hold: u32,
+    idle_throttle: f32,
+    still_rate: f32,
+    landing_hold: u32,
+    warning_elapsed: u32,
+    critical_elapsed: u32,
+    landing_elapsed: u32,
+    compensated_voltage: f32,
+    level: PowerLevel,
+    landed: bool,
+}
+
+impl BatteryFailsafe {
+    /// Constructs a new failsafe at the `Ok` level.
+    pub fn new() -> Self {
+        BatteryFailsafe {
+            internal_resistance: DEFAULT_INTERNAL_RESISTANCE,
+            warning_hold: DEFAULT_WARNING_HOLD,
+            critical_hold: DEFAULT_CRITICAL_HOLD,
+            idle_throttle: DEFAULT_IDLE_THROTTLE,
+            still_rate: DEFAULT_STILL_RATE,
+            landing_hold: DEFAULT_LANDING_HOLD,
+            warning_elapsed: 0,
+            critical_elapsed: 0,
+            landing_elapsed: 0,
+            compensated_voltage: 0.0,
+            level: PowerLevel::Ok,
+            landed: false,
+        }
+    }
+
+    /// Sets the internal resistance of the battery (in ohms), used for sag compensation.
+    pub fn set_internal_resistance(&mut self, resistance: f32) {
+        self.internal_resistance = resistance;
+    }
+
+    /// Sets the times (in microseconds) that the warning and critical thresholds must stay crossed.
+    pub fn set_hold_times(&mut self, warning: u32, critical: u32) {
+        self.warning_hold = warning;
+        self.critical_hold = critical;
+    }
+
+    /// Sets the idle pilot throttle (between 0 and 1), the largest still rotation rate (in degrees per second),
+    /// and the time (in microseconds) that both must hold during a descent before touchdown is detected.
+    pub fn set_touchdown(&mut self, idle_throttle: f32, still_rate: f32, hold: u32) {
+        self.idle_throttle = idle_throttle;
+        self.still_rate = still_rate;
+        self.landing_hold = hold;
+    }
+
+    /// Clears the latched level (for example, after the battery has been replaced).
+    pub fn reset(&mut self) {
+        self.warning_elapsed = 0;
+        self.critical_elapsed = 0;
+        self.landing_elapsed = 0;
+        self.level = PowerLevel::Ok;
+        self.landed = false;
+    }
+
+    /// Updates the failsafe with the latest readings of the power monitor
+    /// and the interval `dt` (in microseconds) since the previous update.
+    /// Nothing happens until the power monitor has detected a battery.
+    pub fn update(&mut self, monitor: &PowerMonitor, dt: u32) -> PowerLevel {
+        if monitor.cells() == 0 {
+            return self.level;
+        }
+
+        let sag: f32 = monitor.current().unwrap_or(0.0)*self.internal_resistance;
+        self.compensated_voltage = monitor.voltage() + sag;
+        let cell_voltage: f32 = self.compensated_voltage/(monitor.cells() as f32);
+
+        let (warning, critical) = match monitor.classify(cell_voltage) {
+            PowerLevel::Ok => (false, false),
+            PowerLevel::Warning => (true, false),
+            PowerLevel::Critical => (true, true),
+        };
+        self.warning_elapsed = if warning { self.warning_elapsed.saturating_add(dt) } else { 0 };
+        self.critical_elapsed = if critical { self.critical_elapsed.saturating_add(dt) } else { 0 };
+
+        if self.critical_elapsed >= self.critical_hold && critical {
+            self.level = PowerLevel::Critical;
+        } else if self.warning_elapsed >= self.warning_hold && warning && self.level == PowerLevel::Ok {
+            self.level = PowerLevel::Warning;
+        }
+        self.level
+    }
+
+    /// Updates touchdown detection during a descent with the pilot throttle (between 0 and 1), the gyroscope rates,
+    /// and the interval `dt` (in microseconds) since the previous update.
+    /// Touchdown is detected once the throttle has stayed at or below the idle throttle,
+    /// and every rate within the still rate, for the landing hold time.
+    pub fn update_touchdown(&mut self, throttle: f32, gyro: &Gyro, dt: u32) {
+        if self.level != PowerLevel::Critical || self.landed {
+            return;
+        }
+
+        let still: bool = Real::abs(gyro.x) <= self.still_rate
+            && Real::abs(gyro.y) <= self.still_rate
+            && Real::abs(gyro.z) <= self.still_rate;
+        self.landing_elapsed = if throttle <= self.idle_throttle && still {
+            self.landing_elapsed.saturating_add(dt)
+        } else {
+            0
+        };
+        self.landed = self.landing_elapsed >= self.landing_hold;
+    }
+
+    /// Confirms touchdown during a descent (for example, when the pilot disarms the motors).
+    pub fn confirm_landed(&mut self) {
+        if self.level == PowerLevel::Critical {
+            self.landed = true;
+        }
+    }
+
+    /// Returns the latched battery level.
+    pub fn level(&self) -> PowerLevel {
+        self.level
+    }
+
+    /// Returns the most recent sag-compensated battery voltage (in volts).
+    pub fn compensated_voltage(&self) -> f32 {
+        self.compensated_voltage
+    }
+
+    /// Returns the flight mode commanded by the failsafe:
+    /// a controlled descent once the battery is depleted, and landed once touchdown has been detected or confirmed.
+    pub fn flight_mode(&self) -> FlightMode {
+        match self.level {
+            PowerLevel::Critical if self.landed => FlightMode::Landed,
+            PowerLevel::Critical => FlightMode::Descent,
+            _ => FlightMode::Stabilized,
+        }
+    }
+
+    /// Reports the latched battery level into the power subsystem status.
+    pub fn report(&self, status: &mut Status) {
+        status.set_power(self.level.status_code());
+    }
+}
+
+impl Default for BatteryFailsafe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
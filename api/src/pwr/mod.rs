@@ -0,0 +1,260 @@
+//! Electrical power monitoring for the Phoenix flight controller.
+//! This module reads the battery voltage (and, optionally, current) from the ADC,
+//! detects the number of cells, integrates the consumed charge, and reports the battery level into the power subsystem status.
+
+use embedded_hal::adc::{
+    Channel,
+    OneShot,
+};
+use sys::{
+    Status,
+    StatusCode,
+};
+
+mod failsafe;
+pub use failsafe::BatteryFailsafe;
+
+
+/// Highest voltage of a fully charged LiPo cell (with some margin), used for detecting the cell count.
+const MAX_CELL_VOLTAGE: f32 = 4.3;
+
+/// Lowest battery voltage at which a battery counts as connected.
+const MIN_BATTERY_VOLTAGE: f32 = 2.0;
+
+/// Default cell voltage below which the battery level is `Warning`.
+const DEFAULT_WARNING_CELL_VOLTAGE: f32 = 3.5;
+
+/// Default cell voltage below which the battery level is `Critical`.
+const DEFAULT_CRITICAL_CELL_VOLTAGE: f32 = 3.3;
+
+/// Default ADC reference voltage.
+const DEFAULT_REFERENCE: f32 = 5.0;
+
+/// Default ADC full-scale reading (for a 10-bit ADC).
+const DEFAULT_FULL_SCALE: u16 = 1023;
+
+
+/// Enumerates battery levels.
+///
+/// # Options
+/// `Ok`: The battery is healthy.
+/// `Warning`: The battery is low; land soon.
+/// `Critical`: The battery is depleted (or not connected); land immediately.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PowerLevel {
+    Ok,
+    Warning,
+    Critical,
+}
+
+impl PowerLevel {
+    /// Returns the subsystem status code corresponding to the battery level.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            PowerLevel::Ok => StatusCode::Ok,
+            PowerLevel::Warning => StatusCode::Warning,
+            PowerLevel::Critical => StatusCode::Critical,
+        }
+    }
+}
+
+
+/// Defines a battery monitor.
+///
+/// The battery voltage reaches the ADC through a resistor divider, so the battery voltage is the ADC voltage times the divider ratio.
+/// A current sensor, if fitted, outputs a voltage proportional to the current (with an optional offset at zero current).
+///
+/// The cell count is detected from the first reading with a battery connected, assuming that the battery was charged.
+///
+/// # Examples
+/// ```
+/// use api::pwr::{PowerLevel, PowerMonitor};
+/// use embedded_hal::adc::{Channel, OneShot};
+/// use sys::{Status, StatusCode};
+///
+/// // Reads a fixed value from every channel of a 10-bit ADC
+/// struct MockAdc(u16);
+/// struct MockPin;
+///
+/// impl Channel<MockAdc> for MockPin {
+///     type ID = u8;
+///     fn channel() -> u8 { 0 }
+/// }
+///
+/// impl OneShot<MockAdc, u16, MockPin> for MockAdc {
+///     type Error = ();
+///     fn read(&mut self, _pin: &mut MockPin) -> nb::Result<u16, ()> { Ok(self.0) }
+/// }
+///
+/// // A 3S battery at 12.6 V behind an 11:1 divider reads 1.145 V
+/// let mut monitor = PowerMonitor::new(11.0);
+/// let mut adc = MockAdc(234);
+/// let voltage = monitor.read_voltage(&mut adc, &mut MockPin).unwrap();
+/// assert!((voltage - 12.6).abs() < 0.05);
+///
+/// monitor.update(voltage, Some(10.0), 1_000_000);
+/// assert_eq!(monitor.cells(), 3);
+/// assert_eq!(monitor.level(), PowerLevel::Ok);
+///
+/// // Drawing 10 A for six minutes consumes 1000 mAh
+/// for _ in 0..359 {
+///     monitor.update(voltage, Some(10.0), 1_000_000);
+/// }
+/// assert!((monitor.consumed() - 1000.0).abs() < 1.0);
+///
+/// // At 3.4 V per cell the battery is low
+/// monitor.update(10.2, Some(10.0), 1000);
+/// assert_eq!(monitor.level(), PowerLevel::Warning);
+///
+/// let mut status = Status::new();
+/// monitor.report(&mut status);
+/// assert!(status.power() == StatusCode::Warning);
+/// ```
+pub struct PowerMonitor {
+    divider: f32,
+    reference: f32,
+    full_scale: u16,
+    current_scale: f32,
+    current_offset: f32,
+    warning_cell_voltage: f32,
+    critical_cell_voltage: f32,
+    cells: u8,
+    voltage: f32,
+    current: Option<f32>,
+    consumed: f32,
+}
+
+impl PowerMonitor {
+    /// Constructs a new battery monitor behind a resistor divider of the given ratio (battery voltage over ADC voltage).
+    pub fn new(divider: f32) -> Self {
+        PowerMonitor {
+            divider: divider,
+            reference: DEFAULT_REFERENCE,
+            full_scale: DEFAULT_FULL_SCALE,
+            current_scale: 1.0,
+            current_offset: 0.0,
+            warning_cell_voltage: DEFAULT_WARNING_CELL_VOLTAGE,
+            critical_cell_voltage: DEFAULT_CRITICAL_CELL_VOLTAGE,
+            cells: 0,
+            voltage: 0.0,
+            current: None,
+            consumed: 0.0,
+        }
+    }
+
+    /// Sets the ADC reference voltage and full-scale reading.
+    pub fn set_adc(&mut self, reference: f32, full_scale: u16) {
+        self.reference = reference;
+        self.full_scale = full_scale;
+    }
+
+    /// Sets the current sensor scale (in amps per volt) and its output (in volts) at zero current.
+    pub fn set_current_sensor(&mut self, scale: f32, offset: f32) {
+        self.current_scale = scale;
+        self.current_offset = offset;
+    }
+
+    /// Sets the cell voltages below which the battery level is `Warning` and `Critical`.
+    pub fn set_cell_thresholds(&mut self, warning: f32, critical: f32) {
+        self.warning_cell_voltage = warning;
+        self.critical_cell_voltage = critical;
+    }
+
+    /// Overrides the detected cell count.
+    /// A cell count of zero detects the cell count again on the next update.
+    pub fn set_cells(&mut self, cells: u8) {
+        self.cells = cells;
+    }
+
+    /// Reads the ADC and returns the voltage at its input.
+    fn read_adc<ADC, W, A, P>(&self, adc: &mut A, pin: &mut P) -> Result<f32, A::Error>
+    where W: Into<u32>, A: OneShot<ADC, W, P>, P: Channel<ADC> {
+        let counts: u32 = nb::block!(adc.read(pin))?.into();
+        Ok((counts as f32)*self.reference/(self.full_scale as f32))
+    }
+
+    /// Reads the battery voltage (in volts) from the given ADC channel.
+    ///
+    /// # Errors
+    /// This function returns an error if the ADC cannot be read.
+    pub fn read_voltage<ADC, W, A, P>(&self, adc: &mut A, pin: &mut P) -> Result<f32, A::Error>
+    where W: Into<u32>, A: OneShot<ADC, W, P>, P: Channel<ADC> {
+        Ok(self.read_adc(adc, pin)?*self.divider)
+    }
+
+    /// Reads the battery current (in amps) from the given ADC channel.
+    ///
+    /// # Errors
+    /// This function returns an error if the ADC cannot be read.
+    pub fn read_current<ADC, W, A, P>(&self, adc: &mut A, pin: &mut P) -> Result<f32, A::Error>
+    where W: Into<u32>, A: OneShot<ADC, W, P>, P: Channel<ADC> {
+        Ok((self.read_adc(adc, pin)? - self.current_offset)*self.current_scale)
+    }
+
+    /// Updates the monitor with the battery voltage (in volts), the battery current (in amps) if measured,
+    /// and the interval `dt` (in microseconds) since the previous update.
+    pub fn update(&mut self, voltage: f32, current: Option<f32>, dt: u32) {
+        self.voltage = voltage;
+        self.current = current;
+
+        if self.cells == 0 && voltage > MIN_BATTERY_VOLTAGE {
+            self.cells = (voltage/MAX_CELL_VOLTAGE) as u8 + 1;
+        }
+
+        // One milliamp-hour is 3.6 amp-seconds
+        if let Some(current) = current {
+            self.consumed += current*(dt as f32)*1e-6/3.6;
+        }
+    }
+
+    /// Returns the most recent battery voltage (in volts).
+    pub fn voltage(&self) -> f32 {
+        self.voltage
+    }
+
+    /// Returns the most recent battery current (in amps), if measured.
+    pub fn current(&self) -> Option<f32> {
+        self.current
+    }
+
+    /// Returns the charge consumed so far (in milliamp-hours).
+    pub fn consumed(&self) -> f32 {
+        self.consumed
+    }
+
+    /// Returns the number of cells, or zero if no battery has been detected.
+    pub fn cells(&self) -> u8 {
+        self.cells
+    }
+
+    /// Returns the most recent voltage per cell (in volts), or zero if no battery has been detected.
+    pub fn cell_voltage(&self) -> f32 {
+        if self.cells == 0 {
+            0.0
+        } else {
+            self.voltage/(self.cells as f32)
+        }
+    }
+
+    /// Returns the battery level for the given voltage per cell.
+    pub fn classify(&self, cell_voltage: f32) -> PowerLevel {
+        if self.cells == 0 || cell_voltage < self.critical_cell_voltage {
+            PowerLevel::Critical
+        } else if cell_voltage < self.warning_cell_voltage {
+            PowerLevel::Warning
+        } else {
+            PowerLevel::Ok
+        }
+    }
+
+    /// Returns the battery level.
+    /// Without a detected battery, the level is `Critical`.
+    pub fn level(&self) -> PowerLevel {
+        self.classify(self.cell_voltage())
+    }
+
+    /// Reports the battery level into the power subsystem status.
+    pub fn report(&self, status: &mut Status) {
+        status.set_power(self.level().status_code());
+    }
+}
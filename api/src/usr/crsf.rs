@@ -0,0 +1,279 @@
+//! CRSF (Crossfire and ExpressLRS) decoder.
+//!
+//! CRSF is a serial protocol at 420000 baud with 8 data bits, no parity, and 1 stop bit.
+//! Each frame holds a sync byte, a length byte (counting the bytes after it), a type byte, a payload, and a CRC-8 (DVB-S2)
+//! over the type and the payload.
+//!
+//! The receiver sends two kinds of frames to the flight controller:
+//! RC channels, with sixteen 11-bit channels packed least significant bit first,
+//! and link statistics, with the signal strength and link quality of the radio link.
+
+use super::{
+    unpack_channels,
+    RcChannels,
+};
+
+
+/// Largest number of bytes in a CRSF frame.
+pub const CRSF_MAX_FRAME_BYTES: usize = 64;
+
+/// First byte of every CRSF frame sent to the flight controller.
+const CRSF_SYNC: u8 = 0xC8;
+
+/// Frame type of link statistics.
+const CRSF_LINK_STATISTICS: u8 = 0x14;
+
+/// Frame type of packed RC channels.
+const CRSF_RC_CHANNELS: u8 = 0x16;
+
+/// Number of bytes in a link statistics payload.
+const LINK_STATISTICS_BYTES: usize = 10;
+
+/// Number of bytes in a packed RC channels payload.
+const RC_CHANNELS_BYTES: usize = 22;
+
+/// Polynomial of the CRC-8 (DVB-S2).
+const CRC_POLYNOMIAL: u8 = 0xD5;
+
+/// Signal strength (in dBm) reported as 0%.
+const RSSI_FLOOR: i16 = -130;
+
+/// Signal strength (in dBm) reported as 100%.
+const RSSI_CEILING: i16 = -50;
+
+
+/// Computes the CRC-8 (DVB-S2) of a sequence of bytes.
+///
+/// # Examples
+/// ```
+/// use api::usr::crsf::crc8;
+///
+/// assert_eq!(crc8(b"123456789"), 0xBC);
+/// ```
+pub fn crc8(bytes: &[u8]) -> u8 {
+    let mut crc: u8 = 0;
+    for byte in bytes.iter() {
+        crc ^= *byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ CRC_POLYNOMIAL } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+
+/// Holds the statistics of the radio link, as reported by the receiver.
+///
+/// # Fields
+/// `uplink_rssi`: Signal strength (in dBm) at each receiver antenna
+/// `uplink_link_quality`: Percentage of frames from the transmitter that arrived
+/// `uplink_snr`: Signal-to-noise ratio (in dB) at the receiver
+/// `active_antenna`: Receiver antenna in use (0 or 1)
+/// `rf_mode`: Packet rate mode of the radio link
+/// `uplink_power`: Transmitter power setting
+/// `downlink_rssi`: Signal strength (in dBm) at the transmitter
+/// `downlink_link_quality`: Percentage of frames from the receiver that arrived
+/// `downlink_snr`: Signal-to-noise ratio (in dB) at the transmitter
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct LinkStatistics {
+    pub uplink_rssi: [i16; 2],
+    pub uplink_link_quality: u8,
+    pub uplink_snr: i8,
+    pub active_antenna: u8,
+    pub rf_mode: u8,
+    pub uplink_power: u8,
+    pub downlink_rssi: i16,
+    pub downlink_link_quality: u8,
+    pub downlink_snr: i8,
+}
+
+impl LinkStatistics {
+    /// Constructs link statistics from the payload of a link statistics frame.
+    fn from_payload(payload: &[u8]) -> Self {
+        // Signal strengths are sent as positive numbers of -dBm
+        LinkStatistics {
+            uplink_rssi: [-(payload[0] as i16), -(payload[1] as i16)],
+            uplink_link_quality: payload[2],
+            uplink_snr: payload[3] as i8,
+            active_antenna: payload[4],
+            rf_mode: payload[5],
+            uplink_power: payload[6],
+            downlink_rssi: -(payload[7] as i16),
+            downlink_link_quality: payload[8],
+            downlink_snr: payload[9] as i8,
+        }
+    }
+
+    /// Returns the signal strength (in dBm) at the active receiver antenna.
+    pub fn rssi(&self) -> i16 {
+        self.uplink_rssi[(self.active_antenna as usize) & 1]
+    }
+
+    /// Returns the signal strength at the active receiver antenna as a percentage,
+    /// scaled from -130 dBm (0%) to -50 dBm (100%) as in Betaflight.
+    pub fn rssi_percent(&self) -> u8 {
+        let rssi: i16 = self.rssi().clamp(RSSI_FLOOR, RSSI_CEILING);
+        ((rssi - RSSI_FLOOR)*100/(RSSI_CEILING - RSSI_FLOOR)) as u8
+    }
+}
+
+
+/// Defines a decoder for a stream of CRSF bytes.
+///
+/// Link statistics are kept and attached to every set of channels decoded after them.
+/// Channel data can contain sync bytes, so a frame with an invalid length or CRC is searched for the next sync byte,
+/// and the decoding restarts from there.
+///
+/// # Examples
+/// ```
+/// use api::usr::pack_channels;
+/// use api::usr::crsf::{crc8, CrsfDecoder};
+///
+/// // Builds a frame from its type and payload
+/// fn frame(kind: u8, payload: &[u8]) -> Vec<u8> {
+///     let mut frame = vec![0xC8, payload.len() as u8 + 2, kind];
+///     frame.extend_from_slice(payload);
+///     frame.push(crc8(&frame[2..]));
+///     frame
+/// }
+///
+/// // Every channel at the top of the stick travel (1811)
+/// let mut packed = [0u8; 22];
+/// pack_channels(&[1811; 16], &mut packed);
+/// let channels = frame(0x16, &packed);
+/// // -60 dBm and -70 dBm at the antennas, 95% link quality, second antenna active
+/// let link = frame(0x14, &[60, 70, 95, 8, 1, 5, 2, 80, 100, 7]);
+///
+/// let mut decoder = CrsfDecoder::new();
+/// let mut decoded = None;
+/// for byte in channels.iter() {
+///     decoded = decoder.push(*byte).or(decoded);
+/// }
+/// let first = decoded.unwrap();
+/// assert_eq!(first.count(), 16);
+/// assert_eq!(first.pulse(0), 2011);
+/// assert_eq!(first.pulse(15), 2011);
+/// assert_eq!(first.rssi(), None);
+///
+/// for byte in link.iter().chain(channels.iter()) {
+///     decoded = decoder.push(*byte).or(decoded);
+/// }
+/// let second = decoded.unwrap();
+/// assert_eq!(decoder.link_statistics().unwrap().rssi(), -70);
+/// assert_eq!(second.rssi(), Some(75));
+/// assert_eq!(second.link_quality(), Some(95));
+///
+/// // A corrupted frame is dropped
+/// let mut corrupted = channels.clone();
+/// corrupted[10] ^= 0x01;
+/// assert!(corrupted.iter().all(|byte| decoder.push(*byte).is_none()));
+///
+/// // One byte of the first frame is dropped, but the decoder still realigns on the second frame
+/// let mut stream = channels.clone();
+/// stream.remove(5);
+/// stream.extend_from_slice(&channels);
+/// let mut decoder = CrsfDecoder::new();
+/// let decoded = stream.iter().filter_map(|byte| decoder.push(*byte)).collect::<Vec<_>>();
+/// assert_eq!(decoded.len(), 1);
+/// assert_eq!(decoded[0].pulse(0), 2011);
+///
+/// // A sync byte in place of the length starts a new frame
+/// let mut decoder = CrsfDecoder::new();
+/// let decoded = [0xC8].iter().chain(channels.iter()).filter_map(|byte| decoder.push(*byte)).count();
+/// assert_eq!(decoded, 1);
+/// ```
+pub struct CrsfDecoder {
+    buffer: [u8; CRSF_MAX_FRAME_BYTES],
+    index: usize,
+    link_statistics: Option<LinkStatistics>,
+}
+
+impl CrsfDecoder {
+    /// Constructs a new CRSF decoder, waiting for a sync byte.
+    pub fn new() -> Self {
+        CrsfDecoder {
+            buffer: [0; CRSF_MAX_FRAME_BYTES],
+            index: 0,
+            link_statistics: None,
+        }
+    }
+
+    /// Returns the latest link statistics, if any have been received.
+    pub fn link_statistics(&self) -> Option<LinkStatistics> {
+        self.link_statistics
+    }
+
+    /// Feeds one byte into the decoder.
+    /// Returns the channels when the byte completes a valid RC channels frame.
+    pub fn push(&mut self, byte: u8) -> Option<RcChannels> {
+        if self.index == 0 && byte != CRSF_SYNC {
+            return None;
+        }
+
+        self.buffer[self.index] = byte;
+        self.index += 1;
+        loop {
+            if self.index < 2 {
+                return None;
+            }
+
+            // The length counts the type, the payload, and the CRC
+            let length: usize = self.buffer[1] as usize;
+            if !(2..=CRSF_MAX_FRAME_BYTES - 2).contains(&length) {
+                self.resync();
+                continue;
+            }
+            let size: usize = length + 2;
+            if self.index < size {
+                return None;
+            }
+
+            let end: usize = size - 1;
+            if crc8(&self.buffer[2..end]) != self.buffer[end] {
+                self.resync();
+                continue;
+            }
+
+            let payload: &[u8] = &self.buffer[3..end];
+            let channels: Option<RcChannels> = match self.buffer[2] {
+                CRSF_LINK_STATISTICS if payload.len() >= LINK_STATISTICS_BYTES => {
+                    self.link_statistics = Some(LinkStatistics::from_payload(payload));
+                    None
+                },
+                CRSF_RC_CHANNELS if payload.len() >= RC_CHANNELS_BYTES => {
+                    let mut channels = RcChannels::new();
+                    unpack_channels(&payload[..RC_CHANNELS_BYTES], &mut channels);
+                    if let Some(link) = self.link_statistics {
+                        channels.set_link(Some(link.rssi_percent()), Some(link.uplink_link_quality));
+                    }
+                    Some(channels)
+                },
+                _ => None,
+            };
+            self.consume(size);
+            return channels;
+        }
+    }
+
+    /// Drops the start of a frame that failed to decode.
+    /// The sync byte may have been a data byte, so the decoding restarts from the next sync byte in the buffer, if any.
+    fn resync(&mut self) {
+        let start: usize = match self.buffer[1..self.index].iter().position(|byte| *byte == CRSF_SYNC) {
+            Some(position) => position + 1,
+            None => self.index,
+        };
+        self.consume(start);
+    }
+
+    /// Removes the first `count` bytes from the buffer.
+    fn consume(&mut self, count: usize) {
+        self.buffer.copy_within(count..self.index, 0);
+        self.index -= count;
+    }
+}
+
+impl Default for CrsfDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,148 @@
+//! FlySky iBUS decoder.
+//!
+//! iBUS is a serial protocol at 115200 baud with 8 data bits, no parity, and 1 stop bit.
+//! Each 32-byte frame holds a 2-byte header, fourteen channels as little-endian pulse widths (in microseconds),
+//! and a little-endian checksum, which is 0xFFFF minus the sum of the other 30 bytes.
+//!
+//! iBUS does not carry the signal strength, but many FlySky receivers can output it on one of the channels.
+
+use super::{
+    RcChannels,
+    MAX_CHANNELS,
+};
+
+
+/// Number of bytes in an iBUS frame.
+pub const IBUS_FRAME_BYTES: usize = 32;
+
+/// Number of channels in an iBUS frame.
+pub const IBUS_CHANNELS: usize = 14;
+
+/// First byte of every iBUS frame (the frame length).
+const IBUS_LENGTH: u8 = 0x20;
+
+/// Second byte of every iBUS frame (the command for channel data).
+const IBUS_COMMAND: u8 = 0x40;
+
+
+/// Decodes one iBUS frame, or returns `None` if the header or checksum is wrong.
+pub fn decode_frame(frame: &[u8; IBUS_FRAME_BYTES]) -> Option<RcChannels> {
+    if frame[0] != IBUS_LENGTH || frame[1] != IBUS_COMMAND {
+        return None;
+    }
+
+    let sum: u16 = frame[..IBUS_FRAME_BYTES - 2].iter().fold(0u16, |sum, byte| sum.wrapping_add(*byte as u16));
+    let checksum: u16 = u16::from_le_bytes([frame[IBUS_FRAME_BYTES - 2], frame[IBUS_FRAME_BYTES - 1]]);
+    if 0xFFFF - sum != checksum {
+        return None;
+    }
+
+    // The upper nibble of each channel is used by some receivers for extra channels, which are not supported
+    let mut channels = RcChannels::new();
+    for channel in 0..IBUS_CHANNELS {
+        let pulse: u16 = u16::from_le_bytes([frame[2 + 2*channel], frame[3 + 2*channel]]);
+        channels.set_pulse(channel, pulse & 0x0FFF);
+    }
+    Some(channels)
+}
+
+
+/// Defines a decoder for a stream of iBUS bytes.
+///
+/// # Examples
+/// ```
+/// use api::usr::ibus::IbusDecoder;
+///
+/// let mut frame = [0u8; 32];
+/// frame[0] = 0x20;
+/// frame[1] = 0x40;
+/// for channel in 0..14 {
+///     let pulse: u16 = 1000 + 50*channel as u16;
+///     frame[2 + 2*channel..4 + 2*channel].copy_from_slice(&pulse.to_le_bytes());
+/// }
+/// let sum = frame[..30].iter().map(|byte| *byte as u16).sum::<u16>();
+/// frame[30..].copy_from_slice(&(0xFFFF - sum).to_le_bytes());
+///
+/// // The receiver outputs the signal strength on the last channel
+/// let mut decoder = IbusDecoder::new();
+/// decoder.set_rssi_channel(Some(13));
+/// let mut decoded = None;
+/// for byte in frame.iter() {
+///     decoded = decoder.push(*byte).or(decoded);
+/// }
+///
+/// let channels = decoded.unwrap();
+/// assert_eq!(channels.count(), 14);
+/// assert_eq!(channels.pulse(0), 1000);
+/// assert_eq!(channels.pulse(10), 1500);
+/// assert_eq!(channels.rssi(), Some(65));
+/// assert_eq!(channels.link_quality(), None);
+///
+/// // A stray length byte before the frame does not hide the frame
+/// let mut decoder = IbusDecoder::new();
+/// assert_eq!([0x20].iter().chain(frame.iter()).filter_map(|byte| decoder.push(*byte)).count(), 1);
+///
+/// // A corrupted frame is dropped
+/// frame[5] ^= 0x01;
+/// assert!(frame.iter().all(|byte| decoder.push(*byte).is_none()));
+/// ```
+pub struct IbusDecoder {
+    buffer: [u8; IBUS_FRAME_BYTES],
+    index: usize,
+    rssi_channel: Option<usize>,
+}
+
+impl IbusDecoder {
+    /// Constructs a new iBUS decoder, waiting for a header.
+    pub fn new() -> Self {
+        IbusDecoder {
+            buffer: [0; IBUS_FRAME_BYTES],
+            index: 0,
+            rssi_channel: None,
+        }
+    }
+
+    /// Sets the channel on which the receiver outputs the signal strength (1000 µs for 0%, 2000 µs for 100%).
+    pub fn set_rssi_channel(&mut self, channel: Option<usize>) {
+        self.rssi_channel = channel.filter(|channel| *channel < MAX_CHANNELS);
+    }
+
+    /// Feeds one byte into the decoder.
+    /// Returns the channels when the byte completes a valid frame.
+    pub fn push(&mut self, byte: u8) -> Option<RcChannels> {
+        let expected: Option<u8> = match self.index {
+            0 => Some(IBUS_LENGTH),
+            1 => Some(IBUS_COMMAND),
+            _ => None,
+        };
+        if matches!(expected, Some(expected) if expected != byte) {
+            // A repeated length byte may be the start of the real frame
+            self.index = 0;
+            if byte == IBUS_LENGTH {
+                self.buffer[0] = byte;
+                self.index = 1;
+            }
+            return None;
+        }
+
+        self.buffer[self.index] = byte;
+        self.index += 1;
+        if self.index < IBUS_FRAME_BYTES {
+            return None;
+        }
+
+        self.index = 0;
+        let mut channels: RcChannels = decode_frame(&self.buffer)?;
+        if let Some(channel) = self.rssi_channel {
+            let rssi: u8 = (channels.throttle(channel)*100.0 + 0.5) as u8;
+            channels.set_link(Some(rssi), None);
+        }
+        Some(channels)
+    }
+}
+
+impl Default for IbusDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,207 @@
+//! RC receiver input for the Phoenix flight controller.
+//! This module decodes the pilot's stick positions from the radio receiver.
+//!
+//! Every decoder produces an `RcChannels` struct, so the rest of the flight controller does not depend on the receiver protocol.
+//! Channels are stored as servo pulse widths, where 1000 µs is one end of the stick travel, 1500 µs is the center, and 2000 µs is the other end.
+
+use math::Real;
+
+pub mod crsf;
+pub mod ibus;
+pub mod ppm;
+pub mod sbus;
+
+
+/// Largest number of channels carried by an `RcChannels` struct.
+pub const MAX_CHANNELS: usize = 16;
+
+/// Pulse width (in microseconds) at the center of the stick travel.
+pub const CENTER_PULSE: u16 = 1500;
+
+/// Pulse width (in microseconds) at either end of the stick travel, measured from the center.
+pub const PULSE_RANGE: u16 = 500;
+
+/// Channel value at the center of the stick travel, for protocols with 11-bit channels (SBUS and CRSF).
+const PACKED_CENTER: i32 = 992;
+
+
+/// Holds one set of receiver channels.
+///
+/// # Examples
+/// ```
+/// use api::usr::RcChannels;
+///
+/// let channels = RcChannels::from_pulses(&[1000, 1500, 2000, 1750]);
+/// assert_eq!(channels.count(), 4);
+/// assert_eq!(channels.value(0), -1.0);
+/// assert_eq!(channels.value(1), 0.0);
+/// assert_eq!(channels.value(3), 0.5);
+/// assert_eq!(channels.throttle(2), 1.0);
+///
+/// // Missing channels read as centered
+/// assert_eq!(channels.pulse(10), 1500);
+/// ```
+#[derive(Copy, Clone)]
+pub struct RcChannels {
+    pulses: [u16; MAX_CHANNELS],
+    count: u8,
+    failsafe: bool,
+    frame_lost: bool,
+    rssi: Option<u8>,
+    link_quality: Option<u8>,
+}
+
+impl RcChannels {
+    /// Constructs a new set of channels with no channels.
+    pub fn new() -> Self {
+        RcChannels {
+            pulses: [CENTER_PULSE; MAX_CHANNELS],
+            count: 0,
+            failsafe: false,
+            frame_lost: false,
+            rssi: None,
+            link_quality: None,
+        }
+    }
+
+    /// Constructs a new set of channels from pulse widths (in microseconds).
+    /// Pulse widths beyond `MAX_CHANNELS` are ignored.
+    pub fn from_pulses(pulses: &[u16]) -> Self {
+        let mut channels = RcChannels::new();
+        for (channel, pulse) in pulses.iter().take(MAX_CHANNELS).enumerate() {
+            channels.set_pulse(channel, *pulse);
+        }
+        channels
+    }
+
+    /// Sets the pulse width (in microseconds) of a channel, extending the channel count if necessary.
+    /// Channels beyond `MAX_CHANNELS` are ignored.
+    pub fn set_pulse(&mut self, channel: usize, pulse: u16) {
+        if channel < MAX_CHANNELS {
+            self.pulses[channel] = pulse;
+            if channel as u8 >= self.count {
+                self.count = channel as u8 + 1;
+            }
+        }
+    }
+
+    /// Returns the number of channels received.
+    pub fn count(&self) -> usize {
+        self.count as usize
+    }
+
+    /// Returns the pulse width (in microseconds) of a channel.
+    /// Channels that were not received read as centered.
+    pub fn pulse(&self, channel: usize) -> u16 {
+        if channel < self.count() {
+            self.pulses[channel]
+        } else {
+            CENTER_PULSE
+        }
+    }
+
+    /// Returns the position of a channel between -1 (1000 µs) and 1 (2000 µs).
+    pub fn value(&self, channel: usize) -> f32 {
+        let value: f32 = ((self.pulse(channel) as f32) - (CENTER_PULSE as f32))/(PULSE_RANGE as f32);
+        Real::clamp(value, -1.0, 1.0)
+    }
+
+    /// Returns the position of a channel between 0 (1000 µs) and 1 (2000 µs), as used for the throttle.
+    pub fn throttle(&self, channel: usize) -> f32 {
+        (self.value(channel) + 1.0)/2.0
+    }
+
+    /// Returns true if the receiver reported that it lost the transmitter and is sending failsafe values.
+    pub fn failsafe(&self) -> bool {
+        self.failsafe
+    }
+
+    /// Returns true if the receiver reported that the last frame from the transmitter was lost.
+    pub fn frame_lost(&self) -> bool {
+        self.frame_lost
+    }
+
+    /// Sets the failsafe and frame-lost flags.
+    pub fn set_flags(&mut self, failsafe: bool, frame_lost: bool) {
+        self.failsafe = failsafe;
+        self.frame_lost = frame_lost;
+    }
+
+    /// Returns the signal strength (in percent), if the receiver reports it.
+    pub fn rssi(&self) -> Option<u8> {
+        self.rssi
+    }
+
+    /// Returns the percentage of frames from the transmitter that arrived, if the receiver reports it.
+    pub fn link_quality(&self) -> Option<u8> {
+        self.link_quality
+    }
+
+    /// Sets the signal strength and link quality (in percent).
+    pub fn set_link(&mut self, rssi: Option<u8>, link_quality: Option<u8>) {
+        self.rssi = rssi;
+        self.link_quality = link_quality;
+    }
+}
+
+impl Default for RcChannels {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+/// Unpacks 11-bit channels, stored least significant bit first, into pulse widths.
+pub(crate) fn unpack_channels(packed: &[u8], channels: &mut RcChannels) {
+    let mut bits: u32 = 0;
+    let mut bit_count: u8 = 0;
+    let mut channel: usize = 0;
+    for byte in packed.iter() {
+        bits |= (*byte as u32) << bit_count;
+        bit_count += 8;
+        while bit_count >= 11 && channel < MAX_CHANNELS {
+            channels.set_pulse(channel, to_pulse((bits & 0x07FF) as u16));
+            bits >>= 11;
+            bit_count -= 11;
+            channel += 1;
+        }
+    }
+}
+
+/// Packs 11-bit channel values least significant bit first, as sent by SBUS and CRSF receivers.
+/// This is the reverse of the decoding, and is mainly useful for testing the decoders.
+///
+/// # Examples
+/// ```
+/// use api::usr::pack_channels;
+///
+/// let mut packed = [0u8; 3];
+/// pack_channels(&[0x7FF, 0x001], &mut packed);
+/// assert_eq!(packed, [0xFF, 0x0F, 0x00]);
+/// ```
+pub fn pack_channels(values: &[u16], packed: &mut [u8]) {
+    let mut bits: u32 = 0;
+    let mut bit_count: u8 = 0;
+    let mut index: usize = 0;
+    for value in values.iter() {
+        bits |= ((*value & 0x07FF) as u32) << bit_count;
+        bit_count += 11;
+        while bit_count >= 8 {
+            if index >= packed.len() {
+                return;
+            }
+            packed[index] = bits as u8;
+            bits >>= 8;
+            bit_count -= 8;
+            index += 1;
+        }
+    }
+    if bit_count > 0 && index < packed.len() {
+        packed[index] = bits as u8;
+    }
+}
+
+/// Converts an 11-bit channel value (172 to 1811 at the ends of the stick travel) into a pulse width (988 µs to 2012 µs).
+fn to_pulse(value: u16) -> u16 {
+    ((value as i32 - PACKED_CENTER)*5/8 + (CENTER_PULSE as i32)) as u16
+}
@@ -0,0 +1,106 @@
+//! PPM (pulse-position modulation) decoder.
+//!
+//! A PPM frame carries every channel on one wire: the time between consecutive edges is the pulse width of one channel,
+//! and a long gap between edges marks the end of the frame.
+//! The decoder is fed the interval between edges, which is usually measured with the input capture unit or a pin-change interrupt and a free-running timer.
+
+use super::{
+    RcChannels,
+    MAX_CHANNELS,
+};
+
+
+/// Shortest interval (in microseconds) that counts as the gap between frames.
+const SYNC_INTERVAL: u32 = 2700;
+
+/// Shortest valid channel pulse (in microseconds).
+const MIN_PULSE: u32 = 750;
+
+/// Longest valid channel pulse (in microseconds).
+const MAX_PULSE: u32 = 2250;
+
+/// Fewest channels in a valid frame.
+const MIN_CHANNELS: usize = 4;
+
+
+/// Defines a PPM decoder.
+///
+/// Frames with a pulse out of range or too few channels are discarded, and the decoder waits for the next gap.
+///
+/// # Examples
+/// ```
+/// use api::usr::ppm::PpmDecoder;
+///
+/// let mut decoder = PpmDecoder::new();
+///
+/// // Nothing is decoded until the first gap between frames
+/// assert!(decoder.edge(1500).is_none());
+/// assert!(decoder.edge(8000).is_none());
+///
+/// for interval in [1000, 1500, 2000, 1250, 1500, 1500].iter() {
+///     assert!(decoder.edge(*interval).is_none());
+/// }
+/// let channels = decoder.edge(9000).unwrap();
+/// assert_eq!(channels.count(), 6);
+/// assert_eq!(channels.pulse(0), 1000);
+/// assert_eq!(channels.pulse(3), 1250);
+///
+/// // A glitch discards the frame
+/// for interval in [1000, 1500, 120, 1250, 1500, 1500].iter() {
+///     decoder.edge(*interval);
+/// }
+/// assert!(decoder.edge(9000).is_none());
+/// ```
+pub struct PpmDecoder {
+    pulses: [u16; MAX_CHANNELS],
+    index: usize,
+    synced: bool,
+}
+
+impl PpmDecoder {
+    /// Constructs a new PPM decoder, waiting for the first gap between frames.
+    pub fn new() -> Self {
+        PpmDecoder {
+            pulses: [0; MAX_CHANNELS],
+            index: 0,
+            synced: false,
+        }
+    }
+
+    /// Feeds the interval (in microseconds) since the previous edge into the decoder.
+    /// Returns the channels when the interval completes a frame.
+    pub fn edge(&mut self, interval: u32) -> Option<RcChannels> {
+        if interval >= SYNC_INTERVAL {
+            let complete: bool = self.synced && self.index >= MIN_CHANNELS;
+            let count: usize = self.index;
+            self.synced = true;
+            self.index = 0;
+            return if complete {
+                Some(RcChannels::from_pulses(&self.pulses[..count]))
+            } else {
+                None
+            };
+        }
+
+        if !self.synced {
+            return None;
+        }
+
+        if !(MIN_PULSE..=MAX_PULSE).contains(&interval) || self.index >= MAX_CHANNELS {
+            // Wait for the next gap before decoding again
+            self.synced = false;
+            self.index = 0;
+            return None;
+        }
+
+        self.pulses[self.index] = interval as u16;
+        self.index += 1;
+        None
+    }
+}
+
+impl Default for PpmDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
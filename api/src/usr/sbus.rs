@@ -0,0 +1,145 @@
+//! SBUS decoder.
+//!
+//! SBUS is an inverted serial protocol at 100000 baud with 8 data bits, even parity, and 2 stop bits.
+//! The inversion must be undone in hardware (or by the UART) before the bytes reach the decoder.
+//!
+//! Each 25-byte frame holds a header byte, sixteen 11-bit channels packed least significant bit first,
+//! a flags byte (digital channels 17 and 18, frame lost, and failsafe), and a footer byte.
+
+use super::{
+    unpack_channels,
+    RcChannels,
+};
+
+
+/// Number of bytes in an SBUS frame.
+pub const SBUS_FRAME_BYTES: usize = 25;
+
+/// First byte of every SBUS frame.
+const SBUS_HEADER: u8 = 0x0F;
+
+/// Flag bit set when the receiver lost the last frame from the transmitter.
+const SBUS_FRAME_LOST: u8 = 0x04;
+
+/// Flag bit set when the receiver is in failsafe.
+const SBUS_FAILSAFE: u8 = 0x08;
+
+
+/// Decodes one SBUS frame, or returns `None` if the header or footer is wrong.
+pub fn decode_frame(frame: &[u8; SBUS_FRAME_BYTES]) -> Option<RcChannels> {
+    // The footer is zero, except for SBUS2, where its low nibble is 4
+    let footer: u8 = frame[SBUS_FRAME_BYTES - 1];
+    if frame[0] != SBUS_HEADER || (footer != 0x00 && footer & 0x0F != 0x04) {
+        return None;
+    }
+
+    let mut channels = RcChannels::new();
+    unpack_channels(&frame[1..23], &mut channels);
+
+    let flags: u8 = frame[23];
+    channels.set_flags(flags & SBUS_FAILSAFE != 0, flags & SBUS_FRAME_LOST != 0);
+    Some(channels)
+}
+
+
+/// Defines a decoder for a stream of SBUS bytes.
+///
+/// Channel data can contain header and footer bytes, so a frame that fails to decode is searched for the next header,
+/// and the decoding restarts from there.
+/// Frames are separated by a gap of at least 3 ms, so the UART idle-line interrupt (or a timeout of about 2 ms without a byte)
+/// should call `reset` to realign on the next frame.
+///
+/// # Examples
+/// ```
+/// use api::usr::pack_channels;
+/// use api::usr::sbus::SbusDecoder;
+///
+/// // Every channel centered (992), with the failsafe flag set
+/// let mut frame = [0u8; 25];
+/// frame[0] = 0x0F;
+/// pack_channels(&[992; 16], &mut frame[1..23]);
+/// frame[23] = 0x08;
+///
+/// // Leading noise is skipped
+/// let mut decoder = SbusDecoder::new();
+/// let mut decoded = None;
+/// for byte in [0x55, 0x00].iter().chain(frame.iter()) {
+///     decoded = decoder.push(*byte).or(decoded);
+/// }
+///
+/// let channels = decoded.unwrap();
+/// assert_eq!(channels.count(), 16);
+/// assert_eq!(channels.pulse(0), 1500);
+/// assert_eq!(channels.pulse(15), 1500);
+/// assert!(channels.failsafe());
+/// assert!(!channels.frame_lost());
+///
+/// // One byte of the first frame is dropped, but the decoder still realigns on the second frame
+/// let mut stream = frame.to_vec();
+/// stream.remove(5);
+/// stream.extend_from_slice(&frame);
+/// let mut decoder = SbusDecoder::new();
+/// let decoded = stream.iter().filter_map(|byte| decoder.push(*byte)).collect::<Vec<_>>();
+/// assert_eq!(decoded.len(), 1);
+/// assert_eq!(decoded[0].pulse(0), 1500);
+///
+/// // With the gap between frames signalled, the damaged frame is discarded at once
+/// let mut decoder = SbusDecoder::new();
+/// for byte in stream[..24].iter() {
+///     assert!(decoder.push(*byte).is_none());
+/// }
+/// decoder.reset();
+/// let decoded = stream[24..].iter().filter_map(|byte| decoder.push(*byte)).collect::<Vec<_>>();
+/// assert_eq!(decoded.len(), 1);
+/// ```
+pub struct SbusDecoder {
+    buffer: [u8; SBUS_FRAME_BYTES],
+    index: usize,
+}
+
+impl SbusDecoder {
+    /// Constructs a new SBUS decoder, waiting for a header byte.
+    pub fn new() -> Self {
+        SbusDecoder {
+            buffer: [0; SBUS_FRAME_BYTES],
+            index: 0,
+        }
+    }
+
+    /// Discards any partial frame, so that the next header byte starts a new frame.
+    /// Call this when the line goes idle between frames.
+    pub fn reset(&mut self) {
+        self.index = 0;
+    }
+
+    /// Feeds one byte into the decoder.
+    /// Returns the channels when the byte completes a valid frame.
+    pub fn push(&mut self, byte: u8) -> Option<RcChannels> {
+        if self.index == 0 && byte != SBUS_HEADER {
+            return None;
+        }
+
+        self.buffer[self.index] = byte;
+        self.index += 1;
+        if self.index < SBUS_FRAME_BYTES {
+            return None;
+        }
+
+        self.index = 0;
+        let channels: Option<RcChannels> = decode_frame(&self.buffer);
+        if channels.is_none() {
+            // The header may have been a data byte, so restart from the next header in the buffer
+            if let Some(start) = self.buffer[1..].iter().position(|byte| *byte == SBUS_HEADER) {
+                self.buffer.copy_within(start + 1.., 0);
+                self.index = SBUS_FRAME_BYTES - (start + 1);
+            }
+        }
+        channels
+    }
+}
+
+impl Default for SbusDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
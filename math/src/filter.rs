@@ -0,0 +1,232 @@
+//! Digital filters for smoothing sensor readings and control terms.
+//!
+//! Every filter implements the `Filter` trait, so the IMU path and the PID derivative term can accept any of them.
+//! Filters are configured from a cutoff (or center) frequency and the sample rate at which `Filter::apply` is called.
+
+use crate::{
+    sin,
+    cos,
+    TWO_PI,
+};
+
+
+/// Quality factor of a second-order Butterworth filter (1/sqrt(2)), which has a maximally flat passband.
+pub const BUTTERWORTH_Q: f32 = core::f32::consts::FRAC_1_SQRT_2;
+
+
+/// Defines a digital filter that processes one sample at a time.
+pub trait Filter {
+    /// Filters one input sample and returns the filtered output.
+    fn apply(&mut self, input: f32) -> f32;
+
+    /// Clears the filter history, as if no samples had been applied.
+    fn reset(&mut self);
+}
+
+
+/// Defines a first-order (PT1) low-pass filter, equivalent to an RC filter.
+///
+/// # Examples
+/// ```
+/// use math::filter::{Filter, Pt1};
+///
+/// // After one time constant, a step input has risen to about 63% of its final value
+/// let mut filter = Pt1::new(10.0, 1000.0);
+/// let time_constant_samples = (1000.0/(2.0*math::PI*10.0)).round() as usize;
+/// let mut output = 0.0;
+/// for _ in 0..time_constant_samples {
+///     output = filter.apply(1.0);
+/// }
+/// assert!((output - 0.63).abs() < 0.02);
+/// ```
+pub struct Pt1 {
+    alpha: f32,
+    state: f32,
+}
+
+impl Pt1 {
+    /// Constructs a new PT1 filter with the given cutoff frequency (in Hz) for samples arriving at `sample_rate` (in Hz).
+    pub fn new(cutoff: f32, sample_rate: f32) -> Self {
+        let mut filter = Pt1 {
+            alpha: 1.0,
+            state: 0.0,
+        };
+        filter.set_cutoff(cutoff, sample_rate);
+        filter
+    }
+
+    /// Changes the cutoff frequency (in Hz) without clearing the filter history.
+    pub fn set_cutoff(&mut self, cutoff: f32, sample_rate: f32) {
+        let rc: f32 = 1.0/(TWO_PI*cutoff);
+        let dt: f32 = 1.0/sample_rate;
+        self.alpha = dt/(rc + dt);
+    }
+}
+
+impl Filter for Pt1 {
+    fn apply(&mut self, input: f32) -> f32 {
+        self.state += self.alpha*(input - self.state);
+        self.state
+    }
+
+    fn reset(&mut self) {
+        self.state = 0.0;
+    }
+}
+
+
+/// Defines a second-order (biquad) filter, implemented in transposed direct form II.
+/// Coefficients follow the Audio EQ Cookbook by Robert Bristow-Johnson.
+///
+/// # Examples
+/// ```
+/// use math::filter::{Biquad, Filter, BUTTERWORTH_Q};
+///
+/// // Returns the largest output after the filter has settled on a sine wave of the given frequency
+/// fn amplitude(filter: &mut Biquad, frequency: f32) -> f32 {
+///     let mut peak: f32 = 0.0;
+///     for i in 0..4000 {
+///         let t = (i as f32)/1000.0;
+///         let output = filter.apply((2.0*math::PI*frequency*t).sin());
+///         if i > 2000 && output.abs() > peak {
+///             peak = output.abs();
+///         }
+///     }
+///     peak
+/// }
+///
+/// // A 50 Hz low-pass filter passes 5 Hz and strongly attenuates 300 Hz
+/// assert!(amplitude(&mut Biquad::low_pass(50.0, 1000.0, BUTTERWORTH_Q), 5.0) > 0.95);
+/// assert!(amplitude(&mut Biquad::low_pass(50.0, 1000.0, BUTTERWORTH_Q), 300.0) < 0.05);
+///
+/// // A 200 Hz notch filter removes 200 Hz and passes 20 Hz
+/// assert!(amplitude(&mut Biquad::notch(200.0, 1000.0, 2.0), 200.0) < 0.05);
+/// assert!(amplitude(&mut Biquad::notch(200.0, 1000.0, 2.0), 20.0) > 0.95);
+/// ```
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    state1: f32,
+    state2: f32,
+}
+
+impl Biquad {
+    /// Constructs a low-pass filter with the given cutoff frequency (in Hz) and quality factor.
+    /// Use `BUTTERWORTH_Q` unless a resonant peak is wanted.  The cutoff must be below half of the sample rate.
+    pub fn low_pass(cutoff: f32, sample_rate: f32, q: f32) -> Self {
+        let (sin_w, cos_w) = angular_frequency(cutoff, sample_rate);
+        let alpha: f32 = sin_w/(2.0*q);
+
+        let b1: f32 = 1.0 - cos_w;
+        Biquad::normalized(0.5*b1, b1, 0.5*b1, 1.0 + alpha, -2.0*cos_w, 1.0 - alpha)
+    }
+
+    /// Constructs a notch filter centered on the given frequency (in Hz).
+    /// Larger quality factors give narrower notches.  The center must be below half of the sample rate.
+    pub fn notch(center: f32, sample_rate: f32, q: f32) -> Self {
+        let (sin_w, cos_w) = angular_frequency(center, sample_rate);
+        let alpha: f32 = sin_w/(2.0*q);
+
+        Biquad::normalized(1.0, -2.0*cos_w, 1.0, 1.0 + alpha, -2.0*cos_w, 1.0 - alpha)
+    }
+
+    /// Constructs a biquad filter from coefficients, dividing through by `a0`.
+    fn normalized(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        Biquad {
+            b0: b0/a0,
+            b1: b1/a0,
+            b2: b2/a0,
+            a1: a1/a0,
+            a2: a2/a0,
+            state1: 0.0,
+            state2: 0.0,
+        }
+    }
+}
+
+impl Filter for Biquad {
+    fn apply(&mut self, input: f32) -> f32 {
+        let output: f32 = self.b0*input + self.state1;
+        self.state1 = self.b1*input - self.a1*output + self.state2;
+        self.state2 = self.b2*input - self.a2*output;
+        output
+    }
+
+    fn reset(&mut self) {
+        self.state1 = 0.0;
+        self.state2 = 0.0;
+    }
+}
+
+/// Returns the sine and cosine of the normalized angular frequency of a filter.
+fn angular_frequency(frequency: f32, sample_rate: f32) -> (f32, f32) {
+    let w: f32 = TWO_PI*frequency/sample_rate;
+    (sin(w), cos(w))
+}
+
+
+/// Defines a moving-average filter over the last `N` samples.
+/// Until `N` samples have been applied, the average is taken over the samples received so far.
+///
+/// # Examples
+/// ```
+/// use math::filter::{Filter, MovingAverage};
+///
+/// let mut filter = MovingAverage::<4>::new();
+/// assert_eq!(filter.apply(4.0), 4.0);
+/// assert_eq!(filter.apply(8.0), 6.0);
+/// filter.apply(0.0);
+/// filter.apply(0.0);
+/// assert_eq!(filter.apply(0.0), 2.0);
+/// ```
+pub struct MovingAverage<const N: usize> {
+    buffer: [f32; N],
+    index: usize,
+    count: usize,
+    sum: f32,
+}
+
+impl<const N: usize> MovingAverage<N> {
+    /// Constructs a new, empty moving-average filter.
+    pub fn new() -> Self {
+        MovingAverage {
+            buffer: [0.0; N],
+            index: 0,
+            count: 0,
+            sum: 0.0,
+        }
+    }
+}
+
+impl<const N: usize> Default for MovingAverage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Filter for MovingAverage<N> {
+    fn apply(&mut self, input: f32) -> f32 {
+        if N == 0 {
+            return input;
+        }
+
+        self.sum += input - self.buffer[self.index];
+        self.buffer[self.index] = input;
+        self.index = (self.index + 1) % N;
+        if self.count < N {
+            self.count += 1;
+        }
+
+        self.sum/(self.count as f32)
+    }
+
+    fn reset(&mut self) {
+        self.buffer = [0.0; N];
+        self.index = 0;
+        self.count = 0;
+        self.sum = 0.0;
+    }
+}
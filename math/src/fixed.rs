@@ -0,0 +1,425 @@
+//! Fixed-point arithmetic for processors without a floating point unit.
+//!
+//! The ATMega328P has no FPU, so every `f32` operation is emulated in software.
+//! `Fixed` stores numbers as Q16.16 (16 integer bits and 16 fractional bits) in an `i32`,
+//! so addition and subtraction are single integer operations and multiplication needs only one wide multiply.
+
+use core::ops::{
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Neg,
+    AddAssign,
+    SubAssign,
+    MulAssign,
+    DivAssign,
+};
+
+
+/// Number of fractional bits in a `Fixed` number.
+pub const FRAC_BITS: u32 = 16;
+
+/// Number of fractional bits used internally by the trigonometric polynomials (Q2.30).
+const POLY_BITS: u32 = 30;
+
+
+/// Holds a signed Q16.16 fixed-point number.
+/// Values range from -32768 to just under 32768 with a resolution of 1/65536.
+///
+/// The arithmetic operators behave like integer operators outside of that range:
+/// `Add`, `Sub`, and `Neg` overflow (and panic in debug builds), while `Mul` and `Div` silently wrap in every build,
+/// and `Div` panics on division by zero.  Use the `saturating_*` methods where the range may be exceeded.
+///
+/// # Examples
+/// ```
+/// use math::fixed::Fixed;
+///
+/// let a = Fixed::from_f32(1.5);
+/// let b = Fixed::from_int(-2);
+/// assert_eq!((a*b).to_f32(), -3.0);
+/// assert_eq!((a/b).to_f32(), -0.75);
+/// assert_eq!(Fixed::MAX.saturating_add(a), Fixed::MAX);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Fixed(i32);
+
+impl Fixed {
+    /// Zero.
+    pub const ZERO: Fixed = Fixed(0);
+
+    /// One.
+    pub const ONE: Fixed = Fixed(1 << FRAC_BITS);
+
+    /// The largest representable value (just under 32768).
+    pub const MAX: Fixed = Fixed(i32::MAX);
+
+    /// The smallest representable value (-32768).
+    pub const MIN: Fixed = Fixed(i32::MIN);
+
+    /// Pi.
+    pub const PI: Fixed = Fixed(205_887);
+
+    /// Pi/2.
+    pub const FRAC_PI_2: Fixed = Fixed(102_944);
+
+    /// 2*pi.
+    pub const TWO_PI: Fixed = Fixed(411_775);
+
+    /// Constructs a `Fixed` number from its raw Q16.16 representation.
+    #[inline(always)]
+    pub const fn from_bits(bits: i32) -> Self {
+        Fixed(bits)
+    }
+
+    /// Returns the raw Q16.16 representation of the number.
+    #[inline(always)]
+    pub const fn to_bits(self) -> i32 {
+        self.0
+    }
+
+    /// Constructs a `Fixed` number from an integer.
+    #[inline(always)]
+    pub const fn from_int(value: i16) -> Self {
+        Fixed((value as i32) << FRAC_BITS)
+    }
+
+    /// Constructs a `Fixed` number from a 32-bit floating point number, saturating outside of the representable range.
+    pub fn from_f32(value: f32) -> Self {
+        // Float-to-integer casts saturate (and map NaN to zero)
+        Fixed((value*((1 << FRAC_BITS) as f32)) as i32)
+    }
+
+    /// Converts the number into a 32-bit floating point number.
+    pub fn to_f32(self) -> f32 {
+        (self.0 as f32)/((1 << FRAC_BITS) as f32)
+    }
+
+    /// Converts raw sensor counts into physical units without using floating point arithmetic.
+    ///
+    /// `full_scale` is the value (in physical units) represented by a reading of 32768 counts,
+    /// such as `8` for an accelerometer set to ±8 g or `1000` for a gyroscope set to ±1000 °/s.
+    ///
+    /// # Examples
+    /// ```
+    /// use math::fixed::Fixed;
+    ///
+    /// // A reading of 4096 counts at ±8 g is exactly 1 g
+    /// assert_eq!(Fixed::from_counts(4096, 8), Fixed::ONE);
+    /// ```
+    pub fn from_counts(counts: i16, full_scale: i16) -> Self {
+        // counts*full_scale/32768 in Q16.16 is counts*full_scale*2
+        Fixed::from_wide(((counts as i64)*(full_scale as i64)) << 1)
+    }
+
+    /// Returns the absolute value of the number, saturating at `Fixed::MAX`.
+    pub fn abs(self) -> Self {
+        Fixed(self.0.saturating_abs())
+    }
+
+    /// Adds two numbers, saturating at the bounds of the representable range.
+    pub fn saturating_add(self, other: Self) -> Self {
+        Fixed(self.0.saturating_add(other.0))
+    }
+
+    /// Subtracts two numbers, saturating at the bounds of the representable range.
+    pub fn saturating_sub(self, other: Self) -> Self {
+        Fixed(self.0.saturating_sub(other.0))
+    }
+
+    /// Multiplies two numbers, saturating at the bounds of the representable range.
+    pub fn saturating_mul(self, other: Self) -> Self {
+        Fixed::from_wide(((self.0 as i64)*(other.0 as i64)) >> FRAC_BITS)
+    }
+
+    /// Divides two numbers, saturating at the bounds of the representable range.
+    /// Division by zero returns `Fixed::MAX`, `Fixed::MIN`, or zero according to the sign of the dividend.
+    pub fn saturating_div(self, other: Self) -> Self {
+        if other.0 == 0 {
+            return if self.0 > 0 {
+                Fixed::MAX
+            } else if self.0 < 0 {
+                Fixed::MIN
+            } else {
+                Fixed::ZERO
+            };
+        }
+        Fixed::from_wide(((self.0 as i64) << FRAC_BITS)/(other.0 as i64))
+    }
+
+    /// Returns the square root of the number.  Non-positive inputs return zero.
+    ///
+    /// # Examples
+    /// ```
+    /// use math::fixed::Fixed;
+    ///
+    /// for i in 0..10_000 {
+    ///     let x = (i as f32)*0.37;
+    ///     assert!((Fixed::from_f32(x).sqrt().to_f32() - x.sqrt()).abs() < 1e-3);
+    /// }
+    /// ```
+    pub fn sqrt(self) -> Self {
+        if self.0 <= 0 {
+            return Fixed::ZERO;
+        }
+
+        // sqrt(bits/2^16)*2^16 = sqrt(bits*2^16)
+        let mut remainder: u64 = (self.0 as u64) << FRAC_BITS;
+        let mut root: u64 = 0;
+        let mut bit: u64 = 1 << 62;
+        while bit > remainder {
+            bit >>= 2;
+        }
+        while bit != 0 {
+            if remainder >= root + bit {
+                remainder -= root + bit;
+                root = (root >> 1) + bit;
+            } else {
+                root >>= 1;
+            }
+            bit >>= 2;
+        }
+        Fixed(root as i32)
+    }
+
+    /// Returns the inverse square root of the number (1/sqrt(x)).
+    /// Non-positive inputs return `Fixed::MAX` instead of dividing by zero.
+    ///
+    /// # Examples
+    /// ```
+    /// use math::fixed::Fixed;
+    ///
+    /// assert_eq!(Fixed::from_int(4).inv_sqrt(), Fixed::from_f32(0.5));
+    /// assert_eq!(Fixed::ZERO.inv_sqrt(), Fixed::MAX);
+    /// assert_eq!(Fixed::from_int(-1).inv_sqrt(), Fixed::MAX);
+    /// ```
+    pub fn inv_sqrt(self) -> Self {
+        Fixed::ONE.saturating_div(self.sqrt())
+    }
+
+    /// Returns the sine of the number (in radians).
+    /// The absolute error is below 1e-4 for |x| up to 20 and grows slowly with |x| beyond that
+    /// (to about 1e-2 at 30000) because 2*pi is rounded to the nearest Q16.16 value.
+    ///
+    /// # Examples
+    /// ```
+    /// use math::fixed::Fixed;
+    ///
+    /// for i in -20_000..=20_000 {
+    ///     let x = (i as f32)*1e-3;
+    ///     assert!((Fixed::from_f32(x).sin().to_f32() - x.sin()).abs() < 1e-4);
+    /// }
+    /// ```
+    pub fn sin(self) -> Self {
+        // Reduce the argument to [-pi, pi]
+        let mut x: i32 = self.0 % Fixed::TWO_PI.0;
+        if x > Fixed::PI.0 {
+            x -= Fixed::TWO_PI.0;
+        } else if x < -Fixed::PI.0 {
+            x += Fixed::TWO_PI.0;
+        }
+
+        // Reduce the argument to [-pi/2, pi/2] using sin(x) = sin(pi - x)
+        if x > Fixed::FRAC_PI_2.0 {
+            x = Fixed::PI.0 - x;
+        } else if x < -Fixed::FRAC_PI_2.0 {
+            x = -Fixed::PI.0 - x;
+        }
+
+        // Evaluate the Taylor series up to the x^11 term in Q2.30
+        const COEFFICIENTS: [i64; 6] = [
+            1 << POLY_BITS,
+            -178_956_971,
+            8_947_849,
+            -213_044,
+            2_959,
+            -27,
+        ];
+        let x: i64 = (x as i64) << (POLY_BITS - FRAC_BITS);
+        let x_squared: i64 = (x*x) >> POLY_BITS;
+        let mut sum: i64 = 0;
+        for coefficient in COEFFICIENTS.iter().rev() {
+            sum = coefficient + ((x_squared*sum) >> POLY_BITS);
+        }
+        Fixed::from_poly((x*sum) >> POLY_BITS)
+    }
+
+    /// Returns the cosine of the number (in radians).
+    /// The absolute error is below 1e-4 for |x| up to 20 (see `Fixed::sin`).
+    pub fn cos(self) -> Self {
+        Fixed(self.0 % Fixed::TWO_PI.0 + Fixed::FRAC_PI_2.0).sin()
+    }
+
+    /// Returns the arctangent of self/x (in radians) in the correct quadrant.
+    /// The result lies between -pi and pi and the absolute error is below 1e-4.
+    ///
+    /// # Examples
+    /// ```
+    /// use math::fixed::Fixed;
+    ///
+    /// for i in 0..3600 {
+    ///     let angle = (i as f32)*core::f32::consts::PI/1800.0 - core::f32::consts::PI;
+    ///     let (y, x) = (100.0*angle.sin(), 100.0*angle.cos());
+    ///     let result = Fixed::from_f32(y).atan2(Fixed::from_f32(x)).to_f32();
+    ///     let error = (result - y.atan2(x)).abs();
+    ///     assert!(error < 1e-4 || (error - 2.0*core::f32::consts::PI).abs() < 1e-4);
+    /// }
+    /// ```
+    pub fn atan2(self, x: Self) -> Self {
+        let y: Self = self;
+        if x.0 == 0 {
+            return if y.0 > 0 {
+                Fixed::FRAC_PI_2
+            } else if y.0 < 0 {
+                -Fixed::FRAC_PI_2
+            } else {
+                Fixed::ZERO
+            };
+        }
+
+        // Reduce the argument to [-1, 1] so that the polynomial remains accurate
+        let (abs_y, abs_x) = ((y.0 as i64).abs(), (x.0 as i64).abs());
+        let same_sign: bool = (y.0 > 0) == (x.0 > 0);
+        let angle: i64 = if abs_y <= abs_x {
+            atan_unit(((y.0 as i64) << POLY_BITS)/(x.0 as i64))
+        } else {
+            let inner: i64 = atan_unit(((x.0 as i64) << POLY_BITS)/(y.0 as i64));
+            let quarter: i64 = (Fixed::FRAC_PI_2.0 as i64) << (POLY_BITS - FRAC_BITS);
+            if same_sign {
+                quarter - inner
+            } else {
+                -quarter - inner
+            }
+        };
+
+        // Move the result into the correct quadrant
+        let angle: Fixed = Fixed::from_poly(angle);
+        if x.0 > 0 {
+            angle
+        } else if y.0 >= 0 {
+            angle + Fixed::PI
+        } else {
+            angle - Fixed::PI
+        }
+    }
+
+    /// Returns the arcsine of the number (in radians).
+    /// Inputs outside of -1 to 1 are clamped to that range.
+    /// The absolute error is below 2e-4 for |x| up to 0.99 and below 1e-3 closer to ±1.
+    pub fn asin(self) -> Self {
+        if self >= Fixed::ONE {
+            return Fixed::FRAC_PI_2;
+        } else if self <= -Fixed::ONE {
+            return -Fixed::FRAC_PI_2;
+        }
+        self.atan2((Fixed::ONE - self*self).sqrt())
+    }
+
+    /// Converts a Q32.16 intermediate result into a `Fixed` number, saturating outside of the representable range.
+    fn from_wide(wide: i64) -> Self {
+        if wide > i32::MAX as i64 {
+            Fixed::MAX
+        } else if wide < i32::MIN as i64 {
+            Fixed::MIN
+        } else {
+            Fixed(wide as i32)
+        }
+    }
+
+    /// Rounds a Q2.30 polynomial result to a `Fixed` number.
+    fn from_poly(value: i64) -> Self {
+        let half: i64 = 1 << (POLY_BITS - FRAC_BITS - 1);
+        Fixed(((value + half) >> (POLY_BITS - FRAC_BITS)) as i32)
+    }
+}
+
+/// Returns the arctangent (in Q2.30) of a Q2.30 number between -1 and 1 using a minimax polynomial.
+fn atan_unit(x: i64) -> i64 {
+    const COEFFICIENTS: [i64; 5] = [
+        1_073_597_943,
+        -354_656_388,
+        193_424_926,
+        -91_410_863,
+        22_371_518,
+    ];
+    let x_squared: i64 = (x*x) >> POLY_BITS;
+    let mut sum: i64 = 0;
+    for coefficient in COEFFICIENTS.iter().rev() {
+        sum = coefficient + ((x_squared*sum) >> POLY_BITS);
+    }
+    (x*sum) >> POLY_BITS
+}
+
+
+impl Add for Fixed {
+    type Output = Fixed;
+
+    #[inline(always)]
+    fn add(self, other: Fixed) -> Fixed {
+        Fixed(self.0 + other.0)
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+
+    #[inline(always)]
+    fn sub(self, other: Fixed) -> Fixed {
+        Fixed(self.0 - other.0)
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Fixed;
+
+    #[inline(always)]
+    fn mul(self, other: Fixed) -> Fixed {
+        Fixed((((self.0 as i64)*(other.0 as i64)) >> FRAC_BITS) as i32)
+    }
+}
+
+impl Div for Fixed {
+    type Output = Fixed;
+
+    #[inline(always)]
+    fn div(self, other: Fixed) -> Fixed {
+        Fixed((((self.0 as i64) << FRAC_BITS)/(other.0 as i64)) as i32)
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Fixed;
+
+    #[inline(always)]
+    fn neg(self) -> Fixed {
+        Fixed(-self.0)
+    }
+}
+
+impl AddAssign for Fixed {
+    #[inline(always)]
+    fn add_assign(&mut self, other: Fixed) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Fixed {
+    #[inline(always)]
+    fn sub_assign(&mut self, other: Fixed) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign for Fixed {
+    #[inline(always)]
+    fn mul_assign(&mut self, other: Fixed) {
+        *self = *self*other;
+    }
+}
+
+impl DivAssign for Fixed {
+    #[inline(always)]
+    fn div_assign(&mut self, other: Fixed) {
+        *self = *self/other;
+    }
+}
@@ -1,7 +1,24 @@
-//! Some simple math functions used by the MPU6050 driver.
+//! Some simple math functions used by the MPU6050 driver and the attitude estimator.
+//!
+//! The `fixed` module provides a fixed-point number type for processors without a floating point unit,
+//! and the `Real` trait allows code to be written once for both `f32` and fixed-point numbers.
+//! `Vector3`, `Quaternion`, and `Matrix3` provide the geometry used by the IMU, GPS, and mixer code.
+//! The `filter` module provides low-pass, notch, and moving-average filters for sensor and control signals.
 
 #![no_std]
 
+pub mod fixed;
+pub mod filter;
+
+mod real;
+pub use real::Real;
+mod vector;
+pub use vector::Vector3;
+mod quaternion;
+pub use quaternion::Quaternion;
+mod matrix;
+pub use matrix::Matrix3;
+
 /// Defines a function that can take integer powers of 32-bit floating numbers.
 ///
 /// # Examples
@@ -23,7 +40,8 @@ pub fn pow(base: f32, power: u8) -> f32 {
 }
 
 /// Returns the **approximate** value for the arctangent of x (in radians).
-/// This requires that the input is between -0.7 and 0.7 to get accurate results.
+/// This requires that the input is between -0.7 and 0.7 to get accurate results (absolute error below 9e-3).
+/// Use `atan2` for an accurate result over the full range of inputs.
 ///
 /// # Examples
 /// ```
@@ -38,4 +56,302 @@ pub fn pow(base: f32, power: u8) -> f32 {
 pub fn arctan(x: f32) -> f32 {
     let arctan_x: f32 = x - pow(x, 3)/3.0 + pow(x, 5)/5.0;
     arctan_x
-}
\ No newline at end of file
+}
+
+/// Defines pi as a 32-bit floating point number.
+pub const PI: f32 = core::f32::consts::PI;
+
+/// Defines pi/2 as a 32-bit floating point number.
+pub const FRAC_PI_2: f32 = core::f32::consts::FRAC_PI_2;
+
+/// Defines 2*pi as a 32-bit floating point number.
+pub const TWO_PI: f32 = 2.0*core::f32::consts::PI;
+
+
+/// Returns the **approximate** inverse square root of x (1/sqrt(x)).
+/// This uses the well-known bit-level initial guess followed by two Newton-Raphson iterations.
+/// The relative error is below 5e-6 for all positive, normal inputs.
+///
+/// # Examples
+/// ```
+/// // Compare against the standard library over the range of normal floating point numbers
+/// let mut x: f32 = 1e-37;
+/// while x < 1e37 {
+///     let expected = 1.0/x.sqrt();
+///     assert!((math::inv_sqrt(x) - expected).abs() < 5e-6*expected);
+///     x *= 1.001;
+/// }
+/// ```
+/// 
+/// # Panics
+/// This function never panics.
+/// 
+/// # Errors
+/// This function returns no errors.  Non-positive inputs produce meaningless results.
+///
+/// # Safety
+/// This function does not require `unsafe` code.
+/// 
+pub fn inv_sqrt(x: f32) -> f32 {
+    let half_x: f32 = 0.5*x;
+    let mut y: f32 = f32::from_bits(0x5f37_59df - (x.to_bits() >> 1));
+    y *= 1.5 - half_x*y*y;
+    y *= 1.5 - half_x*y*y;
+    y
+}
+
+/// Returns the **approximate** arctangent of y/x (in radians) in the correct quadrant.
+/// The result lies between -pi and pi and the absolute error is below 2e-5 for all inputs.
+///
+/// # Examples
+/// ```
+/// let angle = math::atan2(1.0, -1.0);
+/// assert!((angle - 3.0*math::PI/4.0).abs() < 2e-5);
+///
+/// // Compare against the standard library all the way around the circle, at several radii
+/// for radius in [1e-3f32, 1.0, 1e3].iter() {
+///     for i in 0..3600 {
+///         let angle = (i as f32)*math::PI/1800.0 - math::PI;
+///         let (y, x) = (radius*angle.sin(), radius*angle.cos());
+///         let error = (math::atan2(y, x) - y.atan2(x)).abs();
+///         // Results near -pi and pi are equivalent
+///         assert!(error < 2e-5 || (error - math::TWO_PI).abs() < 2e-5);
+///     }
+/// }
+/// ```
+/// 
+/// # Panics
+/// This function never panics.
+/// 
+/// # Errors
+/// This function returns no errors.  `atan2(0.0, 0.0)` returns zero.
+///
+/// # Safety
+/// This function does not require `unsafe` code.
+/// 
+pub fn atan2(y: f32, x: f32) -> f32 {
+    if x == 0.0 {
+        return if y > 0.0 {
+            FRAC_PI_2
+        } else if y < 0.0 {
+            -FRAC_PI_2
+        } else {
+            0.0
+        };
+    }
+
+    // Reduce the argument to [-1, 1] so that the polynomial remains accurate
+    let abs_y: f32 = if y < 0.0 { -y } else { y };
+    let abs_x: f32 = if x < 0.0 { -x } else { x };
+    let angle: f32 = if abs_y <= abs_x {
+        atan_unit(y/x)
+    } else if y/x > 0.0 {
+        FRAC_PI_2 - atan_unit(x/y)
+    } else {
+        -FRAC_PI_2 - atan_unit(x/y)
+    };
+
+    // Move the result into the correct quadrant
+    if x > 0.0 {
+        angle
+    } else if y >= 0.0 {
+        angle + PI
+    } else {
+        angle - PI
+    }
+}
+
+/// Returns the **approximate** arcsine of x (in radians).
+/// Inputs outside of -1 to 1 are clamped to that range.  The absolute error is below 2e-5.
+///
+/// # Examples
+/// ```
+/// let angle = math::asin(0.5);
+/// assert!((angle - math::PI/6.0).abs() < 2e-5);
+///
+/// // Compare against the standard library over the whole domain
+/// for i in -10_000..=10_000 {
+///     let x = (i as f32)*1e-4;
+///     assert!((math::asin(x) - x.asin()).abs() < 2e-5);
+/// }
+/// assert_eq!(math::asin(2.0), math::FRAC_PI_2);
+/// ```
+/// 
+/// # Panics
+/// This function never panics.
+/// 
+/// # Errors
+/// This function returns no errors.
+///
+/// # Safety
+/// This function does not require `unsafe` code.
+/// 
+pub fn asin(x: f32) -> f32 {
+    if x >= 1.0 {
+        return FRAC_PI_2;
+    } else if x <= -1.0 {
+        return -FRAC_PI_2;
+    }
+
+    let cos_squared: f32 = 1.0 - x*x;
+    atan2(x, cos_squared*inv_sqrt(cos_squared))
+}
+
+/// Returns the arctangent of x (in radians) for x between -1 and 1 using a minimax polynomial.
+fn atan_unit(x: f32) -> f32 {
+    let x_squared: f32 = x*x;
+    x*(0.999_866 + x_squared*(-0.330_299_5 + x_squared*(0.180_141 + x_squared*(-0.085_133 + x_squared*0.020_835_1))))
+}
+
+/// Returns the **approximate** square root of x.
+/// The relative error is below 5e-6 for all positive, normal inputs.
+///
+/// # Examples
+/// ```
+/// // Compare against the standard library over the range of normal floating point numbers
+/// let mut x: f32 = 1e-37;
+/// while x < 1e37 {
+///     assert!((math::sqrt(x) - x.sqrt()).abs() < 5e-6*x.sqrt());
+///     x *= 1.001;
+/// }
+/// assert_eq!(math::sqrt(-1.0), 0.0);
+/// ```
+/// 
+/// # Panics
+/// This function never panics.
+/// 
+/// # Errors
+/// This function returns no errors.  Non-positive inputs return zero.
+///
+/// # Safety
+/// This function does not require `unsafe` code.
+/// 
+pub fn sqrt(x: f32) -> f32 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    x*inv_sqrt(x)
+}
+
+/// Returns the **approximate** sine of x (x in radians).
+/// The absolute error is below 1e-6 for |x| up to 10 and grows with |x| beyond that
+/// (to about 1e-5 at 100) because `TWO_PI` is not exactly 2*pi.
+/// For every finite x, the result lies between -1 and 1.
+///
+/// # Examples
+/// ```
+/// // Compare against the standard library over several periods
+/// for i in -100_000..=100_000 {
+///     let x = (i as f32)*1e-4;
+///     assert!((math::sin(x) - x.sin()).abs() < 1e-6);
+/// }
+/// for i in -10_000..=10_000 {
+///     let x = (i as f32)*1e-2;
+///     assert!((math::sin(x) - x.sin()).abs() < 1e-5);
+/// }
+/// ```
+/// 
+/// # Panics
+/// This function never panics.
+/// 
+/// # Errors
+/// This function returns no errors.
+///
+/// # Safety
+/// This function does not require `unsafe` code.
+/// 
+pub fn sin(x: f32) -> f32 {
+    // Reduce the argument to [-pi, pi]
+    // The remainder is exact, so this stays in range even where x/TWO_PI does not fit in an integer
+    let mut x: f32 = x % TWO_PI;
+    if x > PI {
+        x -= TWO_PI;
+    } else if x < -PI {
+        x += TWO_PI;
+    }
+
+    // Reduce the argument to [-pi/2, pi/2] using sin(x) = sin(pi - x)
+    if x > FRAC_PI_2 {
+        x = PI - x;
+    } else if x < -FRAC_PI_2 {
+        x = -PI - x;
+    }
+
+    // Evaluate the Taylor series up to the x^11 term
+    let x_squared: f32 = x*x;
+    x*(1.0 + x_squared*(-1.666_666_7e-1 + x_squared*(8.333_333e-3 + x_squared*(-1.984_127e-4
+        + x_squared*(2.755_732e-6 + x_squared*(-2.505_211e-8))))))
+}
+
+/// Returns the **approximate** cosine of x (x in radians).
+/// The absolute error is below 1e-6 for |x| up to 10 and grows with |x| beyond that
+/// (to about 1e-5 at 100) because `TWO_PI` is not exactly 2*pi.
+/// For every finite x, the result lies between -1 and 1.
+///
+/// # Examples
+/// ```
+/// // Compare against the standard library over several periods
+/// for i in -100_000..=100_000 {
+///     let x = (i as f32)*1e-4;
+///     assert!((math::cos(x) - x.cos()).abs() < 1e-6);
+/// }
+/// for i in -10_000..=10_000 {
+///     let x = (i as f32)*1e-2;
+///     assert!((math::cos(x) - x.cos()).abs() < 1e-5);
+/// }
+/// ```
+/// 
+/// # Panics
+/// This function never panics.
+/// 
+/// # Errors
+/// This function returns no errors.
+///
+/// # Safety
+/// This function does not require `unsafe` code.
+/// 
+pub fn cos(x: f32) -> f32 {
+    // Reduce before shifting, as adding pi/2 to a large x would round it away
+    sin(x % TWO_PI + FRAC_PI_2)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::{
+        sin,
+        cos,
+    };
+
+    /// Visits every finite f32 whose bit pattern is a multiple of a prime stride, which samples every exponent.
+    fn sweep(mut check: impl FnMut(f32)) {
+        for bits in (0..=u32::MAX).step_by(4093) {
+            let x: f32 = f32::from_bits(bits);
+            if x.is_finite() {
+                check(x);
+            }
+        }
+    }
+
+    #[test]
+    fn sin_cos_stay_in_range() {
+        sweep(|x| {
+            let (s, c) = (sin(x), cos(x));
+            assert!((-1.0..=1.0).contains(&s), "sin({}) = {}", x, s);
+            assert!((-1.0..=1.0).contains(&c), "cos({}) = {}", x, c);
+            assert!((s*s + c*c - 1.0).abs() < 1e-5, "sin({0})^2 + cos({0})^2 = {1}", x, s*s + c*c);
+        });
+    }
+
+    #[test]
+    fn sin_cos_match_std_in_documented_domain() {
+        sweep(|x| {
+            let tolerance: f32 = if x.abs() <= 10.0 { 1e-6 } else { 1e-5 };
+            if x.abs() <= 100.0 {
+                assert!((sin(x) - x.sin()).abs() < tolerance, "sin({})", x);
+                assert!((cos(x) - x.cos()).abs() < tolerance, "cos({})", x);
+            }
+        });
+    }
+}
@@ -0,0 +1,142 @@
+//! 3x3 matrices for representing rotations and linear maps.
+
+use core::ops::Mul;
+
+use crate::{
+    Real,
+    Vector3,
+    Quaternion,
+};
+
+
+/// Holds a 3x3 matrix, stored as an array of rows.
+///
+/// # Examples
+/// ```
+/// use math::{Matrix3, Quaternion, Vector3};
+///
+/// // A rotation matrix and the quaternion it came from rotate vectors identically
+/// let q = Quaternion::from_euler(0.3f32, -0.1, 1.2);
+/// let m = Matrix3::from_quaternion(&q);
+/// let v = Vector3::new(1.0, 2.0, 3.0);
+/// assert!((m*v - q.rotate(v)).norm() < 1e-4);
+///
+/// // The transpose of a rotation matrix is its inverse
+/// assert!((m.transpose()*(m*v) - v).norm() < 1e-4);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Matrix3<T> {
+    pub rows: [[T; 3]; 3],
+}
+
+impl<T: Real> Matrix3<T> {
+    /// Constructs a new matrix from an array of rows.
+    #[inline(always)]
+    pub fn from_rows(rows: [[T; 3]; 3]) -> Self {
+        Matrix3 {
+            rows,
+        }
+    }
+
+    /// Constructs the identity matrix.
+    pub fn identity() -> Self {
+        let (o, i) = (T::ZERO, T::ONE);
+        Matrix3::from_rows([
+            [i, o, o],
+            [o, i, o],
+            [o, o, i],
+        ])
+    }
+
+    /// Constructs the rotation by `angle` radians about the X axis.
+    pub fn rotation_x(angle: T) -> Self {
+        let (s, c, o, i) = (angle.sin(), angle.cos(), T::ZERO, T::ONE);
+        Matrix3::from_rows([
+            [i, o, o],
+            [o, c, -s],
+            [o, s, c],
+        ])
+    }
+
+    /// Constructs the rotation by `angle` radians about the Y axis.
+    pub fn rotation_y(angle: T) -> Self {
+        let (s, c, o, i) = (angle.sin(), angle.cos(), T::ZERO, T::ONE);
+        Matrix3::from_rows([
+            [c, o, s],
+            [o, i, o],
+            [-s, o, c],
+        ])
+    }
+
+    /// Constructs the rotation by `angle` radians about the Z axis.
+    pub fn rotation_z(angle: T) -> Self {
+        let (s, c, o, i) = (angle.sin(), angle.cos(), T::ZERO, T::ONE);
+        Matrix3::from_rows([
+            [c, -s, o],
+            [s, c, o],
+            [o, o, i],
+        ])
+    }
+
+    /// Constructs the rotation from roll, pitch, and yaw angles (in radians), using the same convention as `Quaternion::from_euler`.
+    pub fn from_euler(roll: T, pitch: T, yaw: T) -> Self {
+        Matrix3::rotation_z(yaw)*Matrix3::rotation_y(pitch)*Matrix3::rotation_x(roll)
+    }
+
+    /// Constructs the rotation matrix equivalent to a unit quaternion.
+    pub fn from_quaternion(q: &Quaternion<T>) -> Self {
+        let (w, x, y, z) = (q.w, q.x, q.y, q.z);
+        let (i, two) = (T::ONE, T::from_f32(2.0));
+        Matrix3::from_rows([
+            [i - two*(y*y + z*z), two*(x*y - w*z), two*(x*z + w*y)],
+            [two*(x*y + w*z), i - two*(x*x + z*z), two*(y*z - w*x)],
+            [two*(x*z - w*y), two*(y*z + w*x), i - two*(x*x + y*y)],
+        ])
+    }
+
+    /// Returns the transpose of the matrix (the inverse rotation, for a rotation matrix).
+    pub fn transpose(&self) -> Self {
+        let r = &self.rows;
+        Matrix3::from_rows([
+            [r[0][0], r[1][0], r[2][0]],
+            [r[0][1], r[1][1], r[2][1]],
+            [r[0][2], r[1][2], r[2][2]],
+        ])
+    }
+
+    /// Returns the determinant of the matrix.
+    pub fn determinant(&self) -> T {
+        let r = &self.rows;
+        r[0][0]*(r[1][1]*r[2][2] - r[1][2]*r[2][1])
+            - r[0][1]*(r[1][0]*r[2][2] - r[1][2]*r[2][0])
+            + r[0][2]*(r[1][0]*r[2][1] - r[1][1]*r[2][0])
+    }
+}
+
+impl<T: Real> Mul<Vector3<T>> for Matrix3<T> {
+    type Output = Vector3<T>;
+
+    fn mul(self, v: Vector3<T>) -> Vector3<T> {
+        let r = &self.rows;
+        Vector3::new(
+            r[0][0]*v.x + r[0][1]*v.y + r[0][2]*v.z,
+            r[1][0]*v.x + r[1][1]*v.y + r[1][2]*v.z,
+            r[2][0]*v.x + r[2][1]*v.y + r[2][2]*v.z,
+        )
+    }
+}
+
+impl<T: Real> Mul for Matrix3<T> {
+    type Output = Matrix3<T>;
+
+    fn mul(self, other: Matrix3<T>) -> Matrix3<T> {
+        let (a, b) = (&self.rows, &other.rows);
+        let mut rows: [[T; 3]; 3] = [[T::ZERO; 3]; 3];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = a[i][0]*b[0][j] + a[i][1]*b[1][j] + a[i][2]*b[2][j];
+            }
+        }
+        Matrix3::from_rows(rows)
+    }
+}
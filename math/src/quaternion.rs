@@ -0,0 +1,134 @@
+//! Quaternions for representing rotations.
+
+use core::ops::Mul;
+
+use crate::{
+    Real,
+    Vector3,
+};
+
+
+/// Holds a quaternion `w + xi + yj + zk`.
+/// Unit quaternions represent rotations; `q.rotate(v)` rotates `v` by `q`.
+///
+/// Euler angles use the aerospace (Z-Y-X) convention: yaw about Z, then pitch about the new Y axis, then roll about the new X axis.
+///
+/// # Examples
+/// ```
+/// use math::{Quaternion, Vector3, FRAC_PI_2};
+///
+/// // A quarter turn about Z takes X to Y
+/// let q = Quaternion::from_euler(0.0f32, 0.0, FRAC_PI_2);
+/// let v = q.rotate(Vector3::new(1.0, 0.0, 0.0));
+/// assert!((v - Vector3::new(0.0, 1.0, 0.0)).norm() < 1e-4);
+///
+/// // Converting back recovers the angles
+/// let (roll, pitch, yaw) = Quaternion::from_euler(0.1f32, -0.2, 0.3).to_euler();
+/// assert!((roll - 0.1).abs() < 1e-4 && (pitch + 0.2).abs() < 1e-4 && (yaw - 0.3).abs() < 1e-4);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quaternion<T> {
+    pub w: T,
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+impl<T: Real> Quaternion<T> {
+    /// Constructs a new quaternion from its components.
+    #[inline(always)]
+    pub fn new(w: T, x: T, y: T, z: T) -> Self {
+        Quaternion {
+            w,
+            x,
+            y,
+            z,
+        }
+    }
+
+    /// Constructs the quaternion representing no rotation.
+    #[inline(always)]
+    pub fn identity() -> Self {
+        Quaternion::new(T::ONE, T::ZERO, T::ZERO, T::ZERO)
+    }
+
+    /// Constructs the rotation by `angle` radians about `axis` (which need not be normalized).
+    pub fn from_axis_angle(axis: Vector3<T>, angle: T) -> Self {
+        let half: T = angle*T::from_f32(0.5);
+        let axis = axis.normalize()*half.sin();
+        Quaternion::new(half.cos(), axis.x, axis.y, axis.z)
+    }
+
+    /// Constructs a rotation from roll, pitch, and yaw angles (in radians).
+    pub fn from_euler(roll: T, pitch: T, yaw: T) -> Self {
+        let half: T = T::from_f32(0.5);
+        let (sr, cr) = ((roll*half).sin(), (roll*half).cos());
+        let (sp, cp) = ((pitch*half).sin(), (pitch*half).cos());
+        let (sy, cy) = ((yaw*half).sin(), (yaw*half).cos());
+
+        Quaternion {
+            w: cr*cp*cy + sr*sp*sy,
+            x: sr*cp*cy - cr*sp*sy,
+            y: cr*sp*cy + sr*cp*sy,
+            z: cr*cp*sy - sr*sp*cy,
+        }
+    }
+
+    /// Returns the roll, pitch, and yaw angles (in radians) of a unit quaternion.
+    /// Roll and yaw lie between -pi and pi; pitch lies between -pi/2 and pi/2.
+    pub fn to_euler(&self) -> (T, T, T) {
+        let (w, x, y, z) = (self.w, self.x, self.y, self.z);
+        let two: T = T::from_f32(2.0);
+
+        let roll: T = (two*(w*x + y*z)).atan2(T::ONE - two*(x*x + y*y));
+        let pitch: T = (two*(w*y - z*x)).asin();
+        let yaw: T = (two*(w*z + x*y)).atan2(T::ONE - two*(y*y + z*z));
+        (roll, pitch, yaw)
+    }
+
+    /// Returns the conjugate of the quaternion (the inverse rotation, for a unit quaternion).
+    pub fn conjugate(&self) -> Self {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    /// Returns the squared length of the quaternion.
+    pub fn norm_squared(&self) -> T {
+        self.w*self.w + self.x*self.x + self.y*self.y + self.z*self.z
+    }
+
+    /// Returns a quaternion of unit length in the same direction.
+    /// The zero quaternion is returned unchanged.
+    pub fn normalize(self) -> Self {
+        let norm_squared = self.norm_squared();
+        if norm_squared > T::ZERO {
+            let scale: T = norm_squared.inv_sqrt();
+            Quaternion::new(self.w*scale, self.x*scale, self.y*scale, self.z*scale)
+        } else {
+            self
+        }
+    }
+
+    /// Rotates a vector by this unit quaternion.
+    pub fn rotate(&self, v: Vector3<T>) -> Vector3<T> {
+        // v' = v + 2w(u x v) + 2u x (u x v), where u is the vector part of the quaternion
+        let u: Vector3<T> = Vector3::new(self.x, self.y, self.z);
+        let t: Vector3<T> = u.cross(v)*T::from_f32(2.0);
+        v + t*self.w + u.cross(t)
+    }
+}
+
+impl<T: Real> Mul for Quaternion<T> {
+    type Output = Quaternion<T>;
+
+    /// Returns the Hamilton product of two quaternions.
+    /// For unit quaternions, `a*b` is the rotation `b` followed by the rotation `a`.
+    fn mul(self, other: Quaternion<T>) -> Quaternion<T> {
+        let (a, b) = (self, other);
+        Quaternion {
+            w: a.w*b.w - a.x*b.x - a.y*b.y - a.z*b.z,
+            x: a.w*b.x + a.x*b.w + a.y*b.z - a.z*b.y,
+            y: a.w*b.y - a.x*b.z + a.y*b.w + a.z*b.x,
+            z: a.w*b.z + a.x*b.y - a.y*b.x + a.z*b.w,
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! A common interface for floating point and fixed-point numbers.
+//!
+//! The PID controller (`api::pid::ControlVariable`) still uses `f32` directly.
+//! Making it generic over `Real` is left for later, once the fixed-point path has been profiled on the AVR.
+
+use core::ops::{
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Neg,
+};
+
+use crate::fixed::Fixed;
+
+
+/// Defines the operations that control and attitude code needs from a real number.
+/// Code written against `Real` can run on `f32` (for accuracy on the host) or on `Fixed` (for speed on the AVR).
+///
+/// # Examples
+/// ```
+/// use math::Real;
+/// use math::fixed::Fixed;
+///
+/// // Returns the length of the hypotenuse of a right triangle
+/// fn hypot<T: Real>(a: T, b: T) -> T {
+///     (a*a + b*b).sqrt()
+/// }
+///
+/// assert!((hypot(3.0f32, 4.0f32) - 5.0).abs() < 1e-4);
+/// assert_eq!(hypot(Fixed::from_int(3), Fixed::from_int(4)), Fixed::from_int(5));
+/// ```
+pub trait Real:
+    Copy
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    /// Zero.
+    const ZERO: Self;
+
+    /// One.
+    const ONE: Self;
+
+    /// Converts a 32-bit floating point number into this type.
+    fn from_f32(value: f32) -> Self;
+
+    /// Converts this number into a 32-bit floating point number.
+    fn to_f32(self) -> f32;
+
+    /// Returns the absolute value of the number.
+    fn abs(self) -> Self;
+
+    /// Limits the number to lie between `min` and `max`.
+    ///
+    /// # Examples
+    /// ```
+    /// use math::Real;
+    /// use math::fixed::Fixed;
+    ///
+    /// assert_eq!(Real::clamp(1.5f32, -1.0, 1.0), 1.0);
+    /// assert_eq!(Real::clamp(Fixed::from_int(-3), Fixed::from_int(-2), Fixed::ONE), Fixed::from_int(-2));
+    /// ```
+    #[inline(always)]
+    fn clamp(self, min: Self, max: Self) -> Self {
+        if self > max {
+            max
+        } else if self < min {
+            min
+        } else {
+            self
+        }
+    }
+
+    /// Returns the square root of the number (zero for non-positive inputs).
+    fn sqrt(self) -> Self;
+
+    /// Returns the inverse square root of the number (1/sqrt(x)) for positive inputs.
+    fn inv_sqrt(self) -> Self {
+        Self::ONE/self.sqrt()
+    }
+
+    /// Returns the sine of the number (in radians).
+    fn sin(self) -> Self;
+
+    /// Returns the cosine of the number (in radians).
+    fn cos(self) -> Self;
+
+    /// Returns the arctangent of self/x (in radians) in the correct quadrant.
+    fn atan2(self, x: Self) -> Self;
+
+    /// Returns the arcsine of the number (in radians), clamping the input to -1 to 1.
+    fn asin(self) -> Self;
+}
+
+impl Real for f32 {
+    const ZERO: f32 = 0.0;
+    const ONE: f32 = 1.0;
+
+    #[inline(always)]
+    fn from_f32(value: f32) -> f32 {
+        value
+    }
+
+    #[inline(always)]
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    #[inline(always)]
+    fn abs(self) -> f32 {
+        if self < 0.0 { -self } else { self }
+    }
+
+    #[inline(always)]
+    fn sqrt(self) -> f32 {
+        crate::sqrt(self)
+    }
+
+    #[inline(always)]
+    fn inv_sqrt(self) -> f32 {
+        crate::inv_sqrt(self)
+    }
+
+    #[inline(always)]
+    fn sin(self) -> f32 {
+        crate::sin(self)
+    }
+
+    #[inline(always)]
+    fn cos(self) -> f32 {
+        crate::cos(self)
+    }
+
+    #[inline(always)]
+    fn atan2(self, x: f32) -> f32 {
+        crate::atan2(self, x)
+    }
+
+    #[inline(always)]
+    fn asin(self) -> f32 {
+        crate::asin(self)
+    }
+}
+
+impl Real for Fixed {
+    const ZERO: Fixed = Fixed::ZERO;
+    const ONE: Fixed = Fixed::ONE;
+
+    #[inline(always)]
+    fn from_f32(value: f32) -> Fixed {
+        Fixed::from_f32(value)
+    }
+
+    #[inline(always)]
+    fn to_f32(self) -> f32 {
+        Fixed::to_f32(self)
+    }
+
+    #[inline(always)]
+    fn abs(self) -> Fixed {
+        Fixed::abs(self)
+    }
+
+    #[inline(always)]
+    fn sqrt(self) -> Fixed {
+        Fixed::sqrt(self)
+    }
+
+    #[inline(always)]
+    fn inv_sqrt(self) -> Fixed {
+        Fixed::inv_sqrt(self)
+    }
+
+    #[inline(always)]
+    fn sin(self) -> Fixed {
+        Fixed::sin(self)
+    }
+
+    #[inline(always)]
+    fn cos(self) -> Fixed {
+        Fixed::cos(self)
+    }
+
+    #[inline(always)]
+    fn atan2(self, x: Fixed) -> Fixed {
+        Fixed::atan2(self, x)
+    }
+
+    #[inline(always)]
+    fn asin(self) -> Fixed {
+        Fixed::asin(self)
+    }
+}
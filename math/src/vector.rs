@@ -0,0 +1,129 @@
+//! Three-dimensional vectors.
+
+use core::ops::{
+    Add,
+    Sub,
+    Mul,
+    Neg,
+    AddAssign,
+    SubAssign,
+};
+
+use crate::Real;
+
+
+/// Holds a three-dimensional vector, such as an acceleration, an angular rate, or a velocity.
+///
+/// # Examples
+/// ```
+/// use math::Vector3;
+///
+/// let x = Vector3::new(1.0f32, 0.0, 0.0);
+/// let y = Vector3::new(0.0f32, 1.0, 0.0);
+/// assert_eq!(x.cross(y), Vector3::new(0.0, 0.0, 1.0));
+/// assert_eq!(x.dot(y), 0.0);
+/// assert!(((x + y).normalize().norm() - 1.0).abs() < 1e-5);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Vector3<T> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+impl<T: Real> Vector3<T> {
+    /// Constructs a new vector from its components.
+    #[inline(always)]
+    pub fn new(x: T, y: T, z: T) -> Self {
+        Vector3 {
+            x,
+            y,
+            z,
+        }
+    }
+
+    /// Constructs the zero vector.
+    #[inline(always)]
+    pub fn zero() -> Self {
+        Vector3::new(T::ZERO, T::ZERO, T::ZERO)
+    }
+
+    /// Returns the dot product of two vectors.
+    pub fn dot(self, other: Self) -> T {
+        self.x*other.x + self.y*other.y + self.z*other.z
+    }
+
+    /// Returns the cross product of two vectors.
+    pub fn cross(self, other: Self) -> Self {
+        Vector3 {
+            x: self.y*other.z - self.z*other.y,
+            y: self.z*other.x - self.x*other.z,
+            z: self.x*other.y - self.y*other.x,
+        }
+    }
+
+    /// Returns the squared length of the vector.
+    pub fn norm_squared(self) -> T {
+        self.dot(self)
+    }
+
+    /// Returns the length of the vector.
+    pub fn norm(self) -> T {
+        self.norm_squared().sqrt()
+    }
+
+    /// Returns a vector of unit length in the same direction.
+    /// The zero vector is returned unchanged.
+    pub fn normalize(self) -> Self {
+        let norm_squared = self.norm_squared();
+        if norm_squared > T::ZERO {
+            self*norm_squared.inv_sqrt()
+        } else {
+            self
+        }
+    }
+}
+
+impl<T: Real> Add for Vector3<T> {
+    type Output = Vector3<T>;
+
+    fn add(self, other: Vector3<T>) -> Vector3<T> {
+        Vector3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl<T: Real> Sub for Vector3<T> {
+    type Output = Vector3<T>;
+
+    fn sub(self, other: Vector3<T>) -> Vector3<T> {
+        Vector3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl<T: Real> Mul<T> for Vector3<T> {
+    type Output = Vector3<T>;
+
+    fn mul(self, scale: T) -> Vector3<T> {
+        Vector3::new(self.x*scale, self.y*scale, self.z*scale)
+    }
+}
+
+impl<T: Real> Neg for Vector3<T> {
+    type Output = Vector3<T>;
+
+    fn neg(self) -> Vector3<T> {
+        Vector3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl<T: Real> AddAssign for Vector3<T> {
+    fn add_assign(&mut self, other: Vector3<T>) {
+        *self = *self + other;
+    }
+}
+
+impl<T: Real> SubAssign for Vector3<T> {
+    fn sub_assign(&mut self, other: Vector3<T>) {
+        *self = *self - other;
+    }
+}
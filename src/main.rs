@@ -9,14 +9,37 @@ use arduino_hal::spi::Settings;
 
 use api::imu::{
     Mpu6050,
+    Mpu6050Config,
+    AccelRange,
+    GyroRange,
+    DlpfBandwidth,
 };
 #[allow(unused_imports)]
 use api::id::RemoteID;
 #[allow(unused_imports)]
 use api::rf::NRF24L01;
 use api::pid::{
-    ControlVariable,
+    AttitudeController,
+    DerivativeMode,
 };
+use api::ahrs::{
+    Mahony,
+};
+use api::mixer::{
+    Mixer,
+    MAX_MOTORS,
+};
+use api::pwr::{
+    BatteryFailsafe,
+    PowerMonitor,
+};
+use api::imu::Angles;
+use api::mode::{
+    FlightMode,
+    Setpoints,
+    DEFAULT_DESCENT_THROTTLE,
+};
+use sys::Status;
 
 // Implementation of custom `core_unwrap` function for core::result::Result
 trait Unwrap<T, E> {
@@ -137,8 +160,28 @@ fn main() -> ! {
 
     // Create an instance of the Mpu6050 struct to represent the accelerometer
     let mut mpu6050 = Mpu6050::new(i2c);
-    // Wake up the MPU6050
-    mpu6050.wake().unwrap();
+    // Wake up the MPU6050 and configure it for flight
+    // The 44 Hz low-pass filter removes most motor vibration and the 1 kHz sample rate matches the control loop
+    let imu_config = Mpu6050Config {
+        accel_range: AccelRange::G8,
+        gyro_range: GyroRange::Dps1000,
+        dlpf: DlpfBandwidth::Hz44,
+        sample_rate_divider: 0,
+    };
+    mpu6050.init(imu_config).unwrap();
+
+    // Measure the IMU biases while the craft sits still on the ground
+    // This panics (and blinks the LED) if the craft is moved during calibration
+    const IMU_CALIBRATION_SAMPLES: u16 = 500;
+    mpu6050.calibrate(IMU_CALIBRATION_SAMPLES).unwrap();
+
+    // Monitor the battery through the 11:1 resistor divider on A0
+    let mut adc = arduino_hal::Adc::new(peripherals.ADC, Default::default());
+    let mut battery_pin = pins.a0.into_analog_input(&mut adc);
+    const BATTERY_DIVIDER: f32 = 11.0;
+    let mut power = PowerMonitor::new(BATTERY_DIVIDER);
+    let mut failsafe = BatteryFailsafe::new();
+    let mut status = Status::new();
 
     // Create an instance of the NRF24L01 struct to represent the radio transceiver
     let ce = pins.d3.into_output();
@@ -157,22 +200,80 @@ fn main() -> ! {
     // Define a timestep (in microseconds)
     const TIMESTEP: u16 = 1000;
 
-    let mut control_roll = ControlVariable::new(0.0f32, TIMESTEP);
-    let mut control_pitch = ControlVariable::new(0.0f32, TIMESTEP);
+    // Run Timer/Counter 1 freely at 16 MHz / 64, so that each tick is 4 microseconds
+    // This measures the real loop period, which includes the sensor and I2C time on top of the delay
+    const TIMER_TICK_US: u32 = 4;
+    let timer = peripherals.TC1;
+    timer.tccr1b.write(|w| w.cs1().prescale_64());
+    let mut last_tick: u16 = timer.tcnt1.read().bits();
+
+    // The angle loops (in minutes of arc) command rates (in degrees per second) that the gyroscope rate loops track
+    let mut attitude = AttitudeController::new(TIMESTEP);
+
+    // Avoid derivative kick on setpoint changes and keep motor noise out of the derivative term
+    const D_TERM_CUTOFF: f32 = 100.0;
+    // Maximum commanded rates (in degrees per second)
+    const MAX_TILT_RATE: f32 = 200.0;
+    const MAX_YAW_RATE: f32 = 100.0;
+    for (axis, max_rate) in [
+        (&mut attitude.roll, MAX_TILT_RATE),
+        (&mut attitude.pitch, MAX_TILT_RATE),
+        (&mut attitude.yaw, MAX_YAW_RATE),
+    ].iter_mut() {
+        axis.angle_loop().set_gains(0.1, 0.0, 0.0);
+        axis.angle_loop().set_output_limits(-*max_rate, *max_rate);
+        axis.rate_loop().set_derivative_mode(DerivativeMode::Measurement);
+        axis.rate_loop().set_derivative_cutoff(D_TERM_CUTOFF);
+    }
+
+    // Create an attitude estimator that fuses the accelerometer and gyroscope
+    let mut ahrs = Mahony::new(TIMESTEP);
 
-    control_pitch.set_expected(0.0);
+    // Mix the corrections into motor outputs, keeping attitude authority at low throttle
+    let mut mixer = Mixer::quad_x();
+    mixer.set_airmode(true);
 
     loop {
-        #[allow(unused_variables)]
-        let angles = mpu6050.read_angles().unwrap();
+        let motion = mpu6050.read_motion().unwrap();
 
-        control_roll.step(angles.roll);
-        control_pitch.step(angles.pitch);
+        // The 16-bit counter wraps every 262 ms, far longer than one loop iteration
+        let tick: u16 = timer.tcnt1.read().bits();
+        let dt: u32 = (tick.wrapping_sub(last_tick) as u32)*TIMER_TICK_US;
+        last_tick = tick;
 
+        ahrs.update_dt(&motion.accel, &motion.gyro, dt);
+
+        let angles = ahrs.angles();
+
+        let voltage = power.read_voltage(&mut adc, &mut battery_pin).unwrap();
+        power.update(voltage, None, dt);
+        failsafe.update(&power, dt);
+        failsafe.report(&mut status);
+
+        // The receiver decoders (api::usr) are not wired in yet: feeding them needs interrupt-driven UART reception (or input capture for PPM),
+        // which the HAL setup here does not provide, so the pilot holds zero throttle and a level attitude
+        let pilot = Setpoints {
+            throttle: 0.0,
+            angles: Angles { pitch: 0.0, roll: 0.0, yaw: 0.0 },
+        };
+        // A depleted battery overrides the pilot with a controlled descent, which never raises the pilot throttle
+        // and lasts until the craft sits idle and still on the ground
+        failsafe.update_touchdown(pilot.throttle, &motion.gyro, dt);
+        let mode = failsafe.flight_mode();
+        let setpoints = mode.setpoints(pilot, DEFAULT_DESCENT_THROTTLE);
+        attitude.set_expected(&setpoints.angles);
+
+        attitude.step_dt(&angles, &motion.gyro, dt);
+
+        let corrections = attitude.get_corrections();
+
+        // Once touchdown has been detected, the motors stay stopped until the battery is replaced
         #[allow(unused_variables)]
-        let roll_correction = control_roll.get_correction();
-        #[allow(unused_variables)]
-        let pitch_correction = control_pitch.get_correction();
+        let motors = if mode == FlightMode::Landed {
+            [0.0; MAX_MOTORS]
+        } else {
+            mixer.mix(setpoints.throttle, corrections.roll, corrections.pitch, corrections.yaw)
+        };
 
         // Uncomment for debugging purposes only
 
@@ -182,9 +283,9 @@ fn main() -> ! {
             &mut serial,
             "roll: {} | correction: {} || pitch: {} | correction: {}\n",
             angles.roll as i32,
-            roll_correction as i32,
+            corrections.roll as i32,
             angles.pitch as i32,
-            pitch_correction as i32,
+            corrections.pitch as i32,
         ).void_unwrap();
 
         */
@@ -6,10 +6,15 @@
 ///
 /// # Options
 /// `Ok`: All systems are operational.
+/// `Warning`: One or more subsystems need attention soon (for example, a low battery).
+/// `Critical`: One or more subsystems need immediate action (for example, a depleted battery).
 /// `Emergency`: One or more subsystems are in emergency.
 #[repr(u8)]
+#[derive(Copy, Clone, PartialEq)]
 pub enum StatusCode {
     Ok = 0,
+    Warning = 1,
+    Critical = 2,
     Emergency = 255,
 }
 
@@ -22,11 +27,85 @@ pub enum StatusCode {
 /// `remote_id`: FAA-compliant Remote ID subsystem status
 /// `guidance`: positional guidance and PID control subsystems status
 /// `power`: electrical power subsystem status
-#[allow(dead_code)]
+#[derive(Copy, Clone)]
 pub struct Status {
     propulsion: StatusCode,
     radio: StatusCode,
     remote_id: StatusCode,
     guidance: StatusCode,
     power: StatusCode,
+}
+
+impl Status {
+    /// Constructs a new status with every subsystem operational.
+    pub fn new() -> Self {
+        Status {
+            propulsion: StatusCode::Ok,
+            radio: StatusCode::Ok,
+            remote_id: StatusCode::Ok,
+            guidance: StatusCode::Ok,
+            power: StatusCode::Ok,
+        }
+    }
+
+    /// Returns the propulsion subsystem status.
+    pub fn propulsion(&self) -> StatusCode {
+        self.propulsion
+    }
+
+    /// Returns the radio transmission subsystem status.
+    pub fn radio(&self) -> StatusCode {
+        self.radio
+    }
+
+    /// Returns the Remote ID subsystem status.
+    pub fn remote_id(&self) -> StatusCode {
+        self.remote_id
+    }
+
+    /// Returns the guidance and PID control subsystems status.
+    pub fn guidance(&self) -> StatusCode {
+        self.guidance
+    }
+
+    /// Returns the electrical power subsystem status.
+    pub fn power(&self) -> StatusCode {
+        self.power
+    }
+
+    /// Updates the propulsion subsystem status.
+    pub fn set_propulsion(&mut self, status: StatusCode) {
+        self.propulsion = status;
+    }
+
+    /// Updates the radio transmission subsystem status.
+    pub fn set_radio(&mut self, status: StatusCode) {
+        self.radio = status;
+    }
+
+    /// Updates the Remote ID subsystem status.
+    pub fn set_remote_id(&mut self, status: StatusCode) {
+        self.remote_id = status;
+    }
+
+    /// Updates the guidance and PID control subsystems status.
+    pub fn set_guidance(&mut self, status: StatusCode) {
+        self.guidance = status;
+    }
+
+    /// Updates the electrical power subsystem status.
+    pub fn set_power(&mut self, status: StatusCode) {
+        self.power = status;
+    }
+
+    /// Returns true if every subsystem is operational.
+    pub fn is_healthy(&self) -> bool {
+        [self.propulsion, self.radio, self.remote_id, self.guidance, self.power].iter().all(|code| *code == StatusCode::Ok)
+    }
+}
+
+impl Default for Status {
+    fn default() -> Self {
+        Self::new()
+    }
 }
\ No newline at end of file